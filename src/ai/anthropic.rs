@@ -0,0 +1,91 @@
+use std::env;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::backend::{parse_answers, rate_from_env, sanitize_policy, system_instruction, user_prompt, RateLimiter, TransformerBackend};
+
+/// Shared across every `AnthropicBackend` instance so `ANTHROPIC_MAX_REQUESTS_PER_SECOND`
+/// caps total throughput, not just per-request.
+static RATE_LIMITER: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new(rate_from_env("ANTHROPIC_MAX_REQUESTS_PER_SECOND")));
+
+/// Talks to the Anthropic Messages API, selected with
+/// `TRANSFORMER_BACKEND=anthropic`.
+pub struct AnthropicBackend {
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicBackend {
+    pub fn from_env() -> Self {
+        Self {
+            api_key: env::var("ANTHROPIC_API_KEY").unwrap_or_default(),
+            model: env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| "claude-3-5-sonnet-latest".to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    system: String,
+    messages: Vec<Message>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: &'static str,
+    content: String,
+}
+
+#[async_trait]
+impl TransformerBackend for AnthropicBackend {
+    async fn answer(&self, questions: &[String], context: &str) -> Result<Vec<String>> {
+        if self.api_key.is_empty() {
+            return Err(anyhow!("ANTHROPIC_API_KEY not found in env"));
+        }
+
+        let prompt = user_prompt(questions, &sanitize_policy(context));
+        let body = MessagesRequest {
+            model: &self.model,
+            max_tokens: 4096,
+            system: system_instruction(),
+            messages: vec![Message { role: "user", content: prompt }],
+        };
+
+        RATE_LIMITER.wait().await;
+
+        let client = Client::new();
+        let response = client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let raw_text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(anyhow!("Anthropic API request failed: {} - {}", status, raw_text));
+        }
+
+        let json: Value = serde_json::from_str(&raw_text)
+            .map_err(|e| anyhow!("Error deserializing Anthropic response: {}\nRaw response: {}", e, raw_text))?;
+
+        let text = json
+            .get("content")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("text"))
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| anyhow!("Anthropic response missing content[0].text"))?;
+
+        Ok(parse_answers(text))
+    }
+}
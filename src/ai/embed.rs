@@ -1,6 +1,6 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use anyhow::{Result, anyhow};
 use mysql::*;
@@ -10,38 +10,13 @@ use once_cell::sync::Lazy;
 use serde_json;
 use mysql::prelude::*;
 
+use super::embedding::{approx_tokens, EmbeddingProvider};
+use super::hnsw::HnswIndex;
 
 const CHUNK_SIZE: usize = 33000;
 const PARALLEL_REQS: usize = 50;
 const RELEVANT_CHUNKS: usize = 10;
 
-
-#[derive(Serialize)]
-struct EmbedRequest {
-    model: String,
-    content: ContentPart,
-}
-
-#[derive(Serialize)]
-struct ContentPart {
-    parts: Vec<TextPart>,
-}
-
-#[derive(Serialize)]
-struct TextPart {
-    text: String,
-}
-
-#[derive(Deserialize)]
-struct EmbedResponse {
-    embedding: EmbeddingData,
-}
-
-#[derive(Deserialize)]
-struct EmbeddingData {
-    values: Vec<f32>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 struct PdfEmbedding {
     id: Option<i32>,
@@ -49,16 +24,36 @@ struct PdfEmbedding {
     chunk_text: String,
     chunk_index: i32,
     embedding: Vec<f32>,
+    /// The embedding model that produced this row, so a later provider or
+    /// model switch doesn't silently mix incompatible vectors into the
+    /// same similarity search.
+    model: String,
+    /// This chunk's character-offset range in the original extracted text,
+    /// so retrieved context can be traced back to its source location.
+    start_offset: i32,
+    end_offset: i32,
 }
 
 impl PdfEmbedding {
-    fn new(pdf_filename: String, chunk_text: String, chunk_index: i32, embedding: Vec<f32>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        pdf_filename: String,
+        chunk_text: String,
+        chunk_index: i32,
+        embedding: Vec<f32>,
+        model: String,
+        start_offset: i32,
+        end_offset: i32,
+    ) -> Self {
         Self {
             id: None,
             pdf_filename,
             chunk_text,
             chunk_index,
             embedding,
+            model,
+            start_offset,
+            end_offset,
         }
     }
 }
@@ -74,134 +69,439 @@ static DB_POOL: Lazy<Pool> = Lazy::new(|| {
     Pool::new(opts).expect("Failed to create database pool")
 });
 
+/// Format tag written as the first byte of every `embedding` BLOB, so
+/// `unpack_embedding` knows how to read the rest without a separate
+/// schema column. `0x5B` (`[`, the start of a JSON array) is reserved for
+/// detecting rows written before this tagging scheme existed.
+const EMBEDDING_FORMAT_F32: u8 = 1;
+const EMBEDDING_FORMAT_INT8: u8 = 2;
+
+/// Whether to store embeddings as scale + int8 components instead of raw
+/// little-endian f32 - a further ~4x shrink on top of binary packing, at
+/// the cost of some precision. Dequantizing is a single multiply per
+/// component, so this doesn't meaningfully slow down reads. Off by
+/// default; set `EMBEDDING_QUANTIZE_INT8=1` to enable for new writes.
+fn embedding_quantization_enabled() -> bool {
+    std::env::var("EMBEDDING_QUANTIZE_INT8")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+fn unit_vector(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        vector.to_vec()
+    } else {
+        vector.iter().map(|x| x / norm).collect()
+    }
+}
+
+fn pack_embedding_f32(vector: &[f32]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1 + vector.len() * 4);
+    buf.push(EMBEDDING_FORMAT_F32);
+    for v in vector {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+    buf
+}
+
+fn pack_embedding_int8(vector: &[f32]) -> Vec<u8> {
+    let max_abs = vector.iter().fold(0.0f32, |m, v| m.max(v.abs())).max(f32::MIN_POSITIVE);
+    let scale = max_abs / 127.0;
+    let mut buf = Vec::with_capacity(1 + 4 + vector.len());
+    buf.push(EMBEDDING_FORMAT_INT8);
+    buf.extend_from_slice(&scale.to_le_bytes());
+    for v in vector {
+        buf.push((v / scale).round().clamp(-127.0, 127.0) as i8 as u8);
+    }
+    buf
+}
+
+/// Normalizes `vector` to unit length (so cosine similarity against it
+/// reduces to a plain dot product) and packs it per
+/// `embedding_quantization_enabled`.
+fn pack_embedding(vector: &[f32]) -> Vec<u8> {
+    let normalized = unit_vector(vector);
+    if embedding_quantization_enabled() {
+        pack_embedding_int8(&normalized)
+    } else {
+        pack_embedding_f32(&normalized)
+    }
+}
+
+/// Unpacks an `embedding` BLOB written by `pack_embedding`, or - for rows
+/// written before binary packing existed - parses the legacy JSON array
+/// format directly.
+fn unpack_embedding(bytes: &[u8]) -> Result<Vec<f32>> {
+    match bytes.first() {
+        Some(&EMBEDDING_FORMAT_F32) => {
+            let rest = &bytes[1..];
+            if rest.len() % 4 != 0 {
+                return Err(anyhow!("corrupt f32 embedding blob: {} trailing byte(s)", rest.len() % 4));
+            }
+            Ok(rest.chunks_exact(4).map(|c| f32::from_le_bytes(c.try_into().unwrap())).collect())
+        }
+        Some(&EMBEDDING_FORMAT_INT8) => {
+            if bytes.len() < 5 {
+                return Err(anyhow!("corrupt int8 embedding blob: only {} byte(s)", bytes.len()));
+            }
+            let scale = f32::from_le_bytes(bytes[1..5].try_into().unwrap());
+            Ok(bytes[5..].iter().map(|&b| (b as i8) as f32 * scale).collect())
+        }
+        Some(b'[') => {
+            let text = std::str::from_utf8(bytes)
+                .map_err(|e| anyhow!("legacy JSON embedding is not valid UTF-8: {}", e))?;
+            Ok(serde_json::from_str(text)?)
+        }
+        Some(other) => Err(anyhow!("unrecognized embedding blob format tag: {}", other)),
+        None => Err(anyhow!("empty embedding blob")),
+    }
+}
+
 // Batch store multiple embeddings for better performance
-async fn batch_store_pdf_embeddings(pool: &Pool, embeddings: &[PdfEmbedding]) -> Result<()> 
+async fn batch_store_pdf_embeddings(pool: &Pool, embeddings: &[PdfEmbedding]) -> Result<()>
 {
     let mut conn = pool.get_conn()?;
-    let values: Vec<(String, String, i32, String)> = embeddings
+    let values: Vec<(String, String, i32, Vec<u8>, String, i32, i32)> = embeddings
         .iter()
         .map(|record| {
-            let embedding_json = serde_json::to_string(&record.embedding).unwrap();
             (
                 record.pdf_filename.clone(),
                 record.chunk_text.clone(),
                 record.chunk_index,
-                embedding_json,
+                pack_embedding(&record.embedding),
+                record.model.clone(),
+                record.start_offset,
+                record.end_offset,
             )
         })
         .collect();
-    
+
     conn.exec_batch(
-        "INSERT INTO pdf_embeddings (pdf_filename, chunk_text, chunk_index, embedding) VALUES (?, ?, ?, ?)",
+        "INSERT INTO pdf_embeddings (pdf_filename, chunk_text, chunk_index, embedding, model, start_offset, end_offset) VALUES (?, ?, ?, ?, ?, ?, ?)",
         values,
     )?;
-    
+
     Ok(())
 }
 
-// Check if PDF embeddings already exist
-async fn pdf_embeddings_exist(pool: &Pool, pdf_filename: &str) -> Result<bool> {
+// Check if PDF embeddings already exist for the given model - scoped by
+// model so switching `EMBEDDING_PROVIDER` (or just the model name) doesn't
+// silently reuse vectors from an incompatible embedding space. `pdf_filename`
+// is expected to already carry any extraction-scope suffix (see
+// `scoped_pdf_filename`), so a password/page-range-scoped request can never
+// be served the whole-document row set, or vice versa.
+async fn pdf_embeddings_exist(pool: &Pool, pdf_filename: &str, model: &str) -> Result<bool> {
     let mut conn = pool.get_conn()?;
-    
+
     let count: Option<i64> = conn.exec_first(
-        "SELECT COUNT(*) FROM pdf_embeddings WHERE pdf_filename = ?",
-        (pdf_filename,),
+        "SELECT COUNT(*) FROM pdf_embeddings WHERE pdf_filename = ? AND model = ?",
+        (pdf_filename, model),
     )?;
-    
+
     Ok(count.unwrap_or(0) > 0)
 }
 
-// Retrieve embeddings for a specific PDF
-async fn get_pdf_embeddings(pool: &Pool, pdf_filename: &str) -> Result<Vec<PdfEmbedding>> {
+// Retrieve embeddings for a specific PDF, scoped to the given model (and,
+// via `pdf_filename`, to the given extraction scope - see
+// `pdf_embeddings_exist`).
+async fn get_pdf_embeddings(pool: &Pool, pdf_filename: &str, model: &str) -> Result<Vec<PdfEmbedding>> {
     let mut conn = pool.get_conn()?;
-    
-    let results: Vec<(i32, String, String, i32, String)> = conn.exec(
-        "SELECT id, pdf_filename, chunk_text, chunk_index, embedding FROM pdf_embeddings WHERE pdf_filename = ? ORDER BY chunk_index",
-        (pdf_filename,),
+
+    let results: Vec<(i32, String, String, i32, Vec<u8>, String, i32, i32)> = conn.exec(
+        "SELECT id, pdf_filename, chunk_text, chunk_index, embedding, model, start_offset, end_offset FROM pdf_embeddings WHERE pdf_filename = ? AND model = ? ORDER BY chunk_index",
+        (pdf_filename, model),
     )?;
-    
+
     let mut embeddings = Vec::new();
-    for (id, pdf_filename, chunk_text, chunk_index, embedding_str) in results {
-        let embedding: Vec<f32> = serde_json::from_str(&embedding_str)?;
+    for (id, pdf_filename, chunk_text, chunk_index, embedding_bytes, model, start_offset, end_offset) in results {
+        let is_legacy_json = embedding_bytes.first() == Some(&b'[');
+        let mut embedding = unpack_embedding(&embedding_bytes)?;
+
+        if is_legacy_json {
+            // First read of a row written before binary packing existed -
+            // normalize and rewrite it now so later reads skip the
+            // JSON-parse path entirely.
+            embedding = unit_vector(&embedding);
+            conn.exec_drop(
+                "UPDATE pdf_embeddings SET embedding = ? WHERE id = ?",
+                (pack_embedding(&embedding), id),
+            )?;
+        }
+
         embeddings.push(PdfEmbedding {
             id: Some(id),
             pdf_filename,
             chunk_text,
             chunk_index,
             embedding,
+            model,
+            start_offset,
+            end_offset,
         });
     }
-    
+
     Ok(embeddings)
 }
 
 
-/// Chunk text into pieces of exactly max_chars size (may cut words)
-fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
-    text.chars()
-        .collect::<Vec<char>>()
-        .chunks(max_chars)
-        .map(|chunk| chunk.iter().collect::<String>())
-        .filter(|chunk| !chunk.trim().is_empty())
-        .collect()
+/// The token budget a single chunk is greedily packed up to, before
+/// falling back to a new chunk. Tunable via `CHUNK_TOKEN_BUDGET` since the
+/// right chunk size trades off against a provider's `max_batch_tokens` and
+/// the downstream model's context window.
+fn chunk_token_budget() -> usize {
+    std::env::var("CHUNK_TOKEN_BUDGET").ok().and_then(|v| v.parse().ok()).unwrap_or(2000)
 }
 
+/// How many trailing sentences of a chunk are carried into the next one,
+/// so context isn't lost at a chunk boundary. Tunable via
+/// `CHUNK_OVERLAP_SENTENCES`.
+fn chunk_overlap_sentences() -> usize {
+    std::env::var("CHUNK_OVERLAP_SENTENCES").ok().and_then(|v| v.parse().ok()).unwrap_or(2)
+}
 
-async fn get_single_embedding(text: &str, api_key: &str) -> Result<Vec<f32>> {
-    let request_body = EmbedRequest {
-        model: "models/gemini-embedding-001".to_string(),
-        content: ContentPart {
-            parts: vec![TextPart {
-                text: text.to_string(),
-            }],
-        },
-    };
+/// A chunk of policy text carrying the character-offset range it spans in
+/// the original extracted text, so retrieved context can be traced back to
+/// its source location.
+struct TextChunk {
+    text: String,
+    start_offset: usize,
+    end_offset: usize,
+}
 
-    // Check payload size before sending
-    let payload_json = serde_json::to_string(&request_body)?;
-    let payload_size = payload_json.len();
-    
-    if payload_size > 35000 { // Leave some buffer
-        return Err(anyhow!("Payload too large: {} bytes (limit ~36000)", payload_size));
+/// Splits `text` into paragraphs on blank-line boundaries, returning each
+/// paragraph's character-offset range into `text`.
+fn split_paragraphs(text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut paragraphs = Vec::new();
+    let mut start = 0usize;
+    let mut i = 0usize;
+
+    while i < chars.len() {
+        if chars[i] == '\n' && chars.get(i + 1) == Some(&'\n') {
+            if i > start {
+                paragraphs.push((start, i));
+            }
+            while i < chars.len() && (chars[i] == '\n' || chars[i] == '\r') {
+                i += 1;
+            }
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    if start < chars.len() {
+        paragraphs.push((start, chars.len()));
+    }
+
+    paragraphs
+}
+
+/// Splits the paragraph spanning `chars[start..end]` into sentences on
+/// `.`/`!`/`?` followed by whitespace (or the paragraph's end), returning
+/// each sentence's character-offset range into `chars`.
+fn split_sentences(chars: &[char], start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut sentences = Vec::new();
+    let mut sentence_start = start;
+    let mut i = start;
+
+    while i < end {
+        if matches!(chars[i], '.' | '!' | '?') {
+            let boundary = i + 1;
+            if boundary >= end || chars[boundary].is_whitespace() {
+                sentences.push((sentence_start, boundary));
+                let mut j = boundary;
+                while j < end && chars[j].is_whitespace() {
+                    j += 1;
+                }
+                sentence_start = j;
+                i = j;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    if sentence_start < end {
+        sentences.push((sentence_start, end));
+    }
+
+    sentences
+}
+
+/// Builds one `TextChunk` spanning the first through last of `spans` -
+/// valid because `spans` is always a contiguous run of sentences, so
+/// slicing from the first span's start to the last span's end reassembles
+/// exactly the source text for that range (inter-sentence whitespace
+/// included).
+fn spans_to_chunk(chars: &[char], spans: &[(usize, usize)]) -> TextChunk {
+    let start_offset = spans.first().map(|&(s, _)| s).unwrap_or(0);
+    let end_offset = spans.last().map(|&(_, e)| e).unwrap_or(start_offset);
+    let text: String = chars[start_offset..end_offset].iter().collect();
+    TextChunk { text, start_offset, end_offset }
+}
+
+/// Splits `text` into chunks for embedding: breaks first on paragraph
+/// boundaries, then sentences, greedily packs sentences up to
+/// `token_budget` (approximated via chars/4), and carries the last
+/// `overlap_sentences` sentences of a chunk into the next one so context
+/// isn't lost across the boundary. A "sentence" longer than
+/// `hard_char_cap` (a pathological run with no punctuation) is force-split
+/// at the cap, so no single piece exceeds `hard_char_cap` characters; a
+/// piece is not split further, so it can still exceed `token_budget`.
+fn chunk_text_structured(text: &str, token_budget: usize, overlap_sentences: usize, hard_char_cap: usize) -> Vec<TextChunk> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return Vec::new();
     }
-    
-    println!("Sending payload of {} bytes", payload_size);
 
-    let client = Client::new();
-    let response = client
-        .post("https://generativelanguage.googleapis.com/v1beta/models/gemini-embedding-001:embedContent")
-        .header("Content-Type", "application/json")
-        .header("x-goog-api-key", api_key)
-        .json(&request_body)
-        .send()
-        .await?;
+    let mut sentence_spans: Vec<(usize, usize)> = Vec::new();
+    for (p_start, p_end) in split_paragraphs(text) {
+        for (s_start, s_end) in split_sentences(&chars, p_start, p_end) {
+            if s_end - s_start <= hard_char_cap {
+                sentence_spans.push((s_start, s_end));
+            } else {
+                let mut pos = s_start;
+                while pos < s_end {
+                    let piece_end = (pos + hard_char_cap).min(s_end);
+                    sentence_spans.push((pos, piece_end));
+                    pos = piece_end;
+                }
+            }
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut current: Vec<(usize, usize)> = Vec::new();
+    let mut current_tokens = 0usize;
 
-    let status = response.status();
-    let raw_text = response.text().await?;
+    for (s_start, s_end) in sentence_spans {
+        let sentence_tokens = ((s_end - s_start) / 4).max(1);
+
+        if !current.is_empty() && current_tokens + sentence_tokens > token_budget {
+            chunks.push(spans_to_chunk(&chars, &current));
+
+            let overlap_start = current.len().saturating_sub(overlap_sentences);
+            current = current[overlap_start..].to_vec();
+            current_tokens = current.iter().map(|&(a, b)| ((b - a) / 4).max(1)).sum();
+        }
 
-    if !status.is_success() {
-        return Err(anyhow!("Gemini Embeddings API request failed: {} - {}", status, raw_text));
+        current.push((s_start, s_end));
+        current_tokens += sentence_tokens;
     }
 
-    let embed_response: EmbedResponse = serde_json::from_str(&raw_text)
-        .map_err(|e| anyhow!("Error deserializing embedding response: {}\nRaw response: {}", e, raw_text))?;
+    if !current.is_empty() {
+        chunks.push(spans_to_chunk(&chars, &current));
+    }
 
-    Ok(embed_response.embedding.values)
+    chunks.retain(|c| !c.text.trim().is_empty());
+    chunks
 }
 
+/// Groups `chunks` into batches whose combined approximate token count
+/// stays under `max_batch_tokens`, so `get_policy_chunk_embeddings` sizes
+/// requests per-provider instead of the old hardcoded 35000-byte check.
+/// A single chunk over the limit still gets its own (oversized) batch
+/// rather than being silently dropped.
+fn batch_chunks_by_token_budget(chunks: Vec<TextChunk>, max_batch_tokens: usize) -> Vec<Vec<TextChunk>> {
+    let mut batches = Vec::new();
+    let mut current_batch: Vec<TextChunk> = Vec::new();
+    let mut current_tokens = 0usize;
+
+    for chunk in chunks {
+        let chunk_tokens = approx_tokens(&chunk.text);
+        if !current_batch.is_empty() && current_tokens + chunk_tokens > max_batch_tokens {
+            batches.push(std::mem::take(&mut current_batch));
+            current_tokens = 0;
+        }
+        current_tokens += chunk_tokens;
+        current_batch.push(chunk);
+    }
+
+    if !current_batch.is_empty() {
+        batches.push(current_batch);
+    }
+
+    batches
+}
 
 /// Alternative: Return all chunk embeddings instead of averaging
 use futures::stream::{self, StreamExt};
 
-pub async fn get_policy_chunk_embeddings(api_key: &str, pdf_filename: &str) -> Result<Vec<(String, Vec<f32>)>> {
+/// Fingerprints a non-default extraction scope (a password and/or a page
+/// range, see `pdf::PdfExtractOpts`) so `get_policy_chunk_embeddings` can
+/// fold it into its cache keys. `None` for the default (whole-document,
+/// unencrypted) scope, matching `opts_are_default` in `server.rs`'s
+/// `run_pipeline` - the exact password isn't part of the fingerprint since
+/// a correct password for a given document always decrypts to the same
+/// text, only *whether* one was supplied matters.
+pub fn extraction_scope_fingerprint(has_password: bool, page_range: Option<(usize, usize)>) -> Option<String> {
+    if !has_password && page_range.is_none() {
+        return None;
+    }
+    let page_range = match page_range {
+        Some((start, end)) => format!("{}-{}", start, end),
+        None => "full".to_string(),
+    };
+    Some(format!("pw{}_pg{}", has_password as u8, page_range))
+}
+
+/// `pdf_filename` suffixed with `scope`, if any, so the MySQL
+/// `pdf_embeddings` table can key a scoped extraction's chunks separately
+/// from the whole-document set under the same filename.
+fn scoped_pdf_filename(pdf_filename: &str, scope: Option<&str>) -> String {
+    match scope {
+        Some(scope) => format!("{}::{}", pdf_filename, scope),
+        None => pdf_filename.to_string(),
+    }
+}
+
+/// `content_hash`, when known (the caller already downloaded and hashed
+/// the document), lets this dedup against the content-addressed `Store`
+/// ahead of the `pdf_filename`-keyed MySQL lookup below - two different
+/// URLs/filenames serving byte-identical documents then share one
+/// embedding set instead of each re-embedding from scratch, the same
+/// dedup the document and extracted-text caches in `store.rs` already
+/// give the pipeline's earlier stages. Pass `None` when no content hash
+/// is available (e.g. `/api/v1/search`, which only ever reads
+/// already-embedded documents by filename).
+///
+/// `scope`, from `extraction_scope_fingerprint`, distinguishes a
+/// password/page-range-scoped extraction from the whole-document one so
+/// the two never share a cache entry or DB row set under the same
+/// `pdf_filename`/content hash - see that function's doc comment.
+pub async fn get_policy_chunk_embeddings(
+    provider: &dyn EmbeddingProvider,
+    pdf_filename: &str,
+    content_hash: Option<&str>,
+    scope: Option<&str>,
+) -> Result<Vec<(String, Vec<f32>, i32, i32)>> {
     let pool = &*DB_POOL;
-    
-    // Check if embeddings already exist
-    if pdf_embeddings_exist(pool, pdf_filename).await? {
-        println!("Embeddings for {} already exist, retrieving from database", pdf_filename);
-        let stored_embeddings = get_pdf_embeddings(pool, pdf_filename).await?;
+    let store = crate::store::default_store();
+    let cache_key = content_hash.map(|hash| crate::store::embeddings_key(hash, provider.model_name(), scope));
+    let db_pdf_filename = scoped_pdf_filename(pdf_filename, scope);
+
+    if let Some(key) = &cache_key {
+        if let Some(cached) = store.get(key).await? {
+            if let Ok(cached_embeddings) = serde_json::from_slice::<Vec<PdfEmbedding>>(&cached) {
+                println!("Reusing cached embeddings for content hash (model {})", provider.model_name());
+                return Ok(cached_embeddings.into_iter()
+                    .map(|e| (e.chunk_text, e.embedding, e.start_offset, e.end_offset))
+                    .collect());
+            }
+        }
+    }
+
+    // Check if embeddings already exist for this model
+    if pdf_embeddings_exist(pool, &db_pdf_filename, provider.model_name()).await? {
+        println!("Embeddings for {} ({}) already exist, retrieving from database", db_pdf_filename, provider.model_name());
+        let stored_embeddings = get_pdf_embeddings(pool, &db_pdf_filename, provider.model_name()).await?;
+        if let Some(key) = &cache_key {
+            if let Ok(serialized) = serde_json::to_vec(&stored_embeddings) {
+                store.put(key, &serialized).await?;
+            }
+        }
         return Ok(stored_embeddings.into_iter()
-            .map(|e| (e.chunk_text, e.embedding))
+            .map(|e| (e.chunk_text, e.embedding, e.start_offset, e.end_offset))
             .collect());
     }
 
@@ -210,127 +510,482 @@ pub async fn get_policy_chunk_embeddings(api_key: &str, pdf_filename: &str) -> R
     if !policy_path.exists() {
         return Err(anyhow!("File {:?} does not exist", policy_path));
     }
-    
+
     let policy_content = fs::read_to_string(policy_path)?;
-    let chunks = chunk_text(&policy_content, CHUNK_SIZE);
+    let chunks = chunk_text_structured(&policy_content, chunk_token_budget(), chunk_overlap_sentences(), CHUNK_SIZE);
     let total_chunks = chunks.len(); // Store length before moving
-    
-    println!("Processing {} chunks for {} with controlled parallelism", total_chunks, pdf_filename);
-    
-    // Process chunks in parallel with limited concurrency
-    let chunk_embeddings: Vec<_> = stream::iter(chunks.into_iter().enumerate())
-        .map(|(i, chunk)| {
-            let pdf_filename = pdf_filename.to_string(); // Clone for move
-            async move {
-                println!("Processing chunk {} of {} for {}", i + 1, total_chunks, pdf_filename);
-                
-                let embedding = get_single_embedding(&chunk, api_key).await?;
-                Ok::<(String, Vec<f32>), anyhow::Error>((chunk, embedding))
-            }
+
+    let batches = batch_chunks_by_token_budget(chunks, provider.max_batch_tokens());
+    println!(
+        "Processing {} chunks for {} in {} batches (provider: {})",
+        total_chunks, pdf_filename, batches.len(), provider.model_name()
+    );
+
+    // Embed batches in parallel with limited concurrency; each batch is one
+    // request to the provider, carrying as many chunks as fit its
+    // `max_batch_tokens`.
+    let batch_results: Vec<_> = stream::iter(batches.into_iter().enumerate())
+        .map(|(i, batch)| async move {
+            println!("Embedding batch {} ({} chunks) for {}", i, batch.len(), pdf_filename);
+            let texts: Vec<String> = batch.iter().map(|chunk| chunk.text.clone()).collect();
+            let embeddings = provider.embed(&texts).await?;
+            Ok::<(Vec<TextChunk>, Vec<Vec<f32>>), anyhow::Error>((batch, embeddings))
         })
         .buffer_unordered(PARALLEL_REQS)
         .collect::<Vec<_>>()
         .await;
-    
+
     // Handle any errors and prepare for database storage
     let mut results = Vec::new();
     let mut db_records = Vec::new();
-    
-    for (index, result) in chunk_embeddings.into_iter().enumerate() {
-        let (chunk_text, embedding) = result?;
-        results.push((chunk_text.clone(), embedding.clone()));
-        
-        db_records.push(PdfEmbedding::new(
-            pdf_filename.to_string(),
-            chunk_text,
-            index as i32,
-            embedding,
-        ));
+    let mut index = 0i32;
+
+    for batch_result in batch_results {
+        let (batch_chunks, batch_embeddings) = batch_result?;
+        if batch_chunks.len() != batch_embeddings.len() {
+            return Err(anyhow!(
+                "embedding provider returned {} vectors for a batch of {} chunks",
+                batch_embeddings.len(),
+                batch_chunks.len()
+            ));
+        }
+
+        for (chunk, embedding) in batch_chunks.into_iter().zip(batch_embeddings.into_iter()) {
+            results.push((chunk.text.clone(), embedding.clone(), chunk.start_offset as i32, chunk.end_offset as i32));
+            db_records.push(PdfEmbedding::new(
+                db_pdf_filename.clone(),
+                chunk.text,
+                index,
+                embedding,
+                provider.model_name().to_string(),
+                chunk.start_offset as i32,
+                chunk.end_offset as i32,
+            ));
+            index += 1;
+        }
     }
-    
+
     // Store all embeddings in database
     println!("Storing {} embeddings in database for {}", db_records.len(), pdf_filename);
     batch_store_pdf_embeddings(pool, &db_records).await?;
-    
+
+    if let Some(key) = &cache_key {
+        if let Ok(serialized) = serde_json::to_vec(&db_records) {
+            store.put(key, &serialized).await?;
+        }
+    }
+
     println!("Successfully processed and stored {} chunks for {}", results.len(), pdf_filename);
     Ok(results)
 }
 
 
 
+/// Reciprocal Rank Fusion's discount constant - the usual default (~60)
+/// dampens how much a #1 rank dominates the fused score while still
+/// rewarding it over #2, #3, ...
+const RRF_K: f32 = 60.0;
+
+/// BM25's term-frequency saturation and length-normalization constants -
+/// the standard defaults (Robertson/Zaragoza).
+const BM25_K1: f32 = 1.5;
+const BM25_B: f32 = 0.75;
+
+/// How much weight the vector (cosine similarity) ranking gets in
+/// `reciprocal_rank_fusion`, relative to `lexical_weight`. Both default to
+/// `1.0` (plain, unweighted RRF); raise `RETRIEVAL_VECTOR_WEIGHT` to favor
+/// semantic similarity over exact lexical overlap.
+fn vector_weight() -> f32 {
+    std::env::var("RETRIEVAL_VECTOR_WEIGHT").ok().and_then(|v| v.parse().ok()).unwrap_or(1.0)
+}
+
+/// How much weight the BM25 (lexical) ranking gets in
+/// `reciprocal_rank_fusion`. Raise `RETRIEVAL_LEXICAL_WEIGHT` to favor
+/// exact-term matches (policy numbers, clause IDs) that embeddings tend to
+/// blur together.
+fn lexical_weight() -> f32 {
+    std::env::var("RETRIEVAL_LEXICAL_WEIGHT").ok().and_then(|v| v.parse().ok()).unwrap_or(1.0)
+}
+
+/// How many approximate-nearest-neighbor candidates the HNSW index hands
+/// to the BM25/RRF fusion step, ahead of it narrowing that down to
+/// `RELEVANT_CHUNKS`. Wider than `RELEVANT_CHUNKS` so a chunk that's
+/// lexically strong but only the Nth-nearest vector still has a chance
+/// to win the fused ranking. Override with `ANN_CANDIDATE_POOL`.
+fn ann_candidate_pool() -> usize {
+    std::env::var("ANN_CANDIDATE_POOL").ok().and_then(|v| v.parse().ok()).unwrap_or(50)
+}
+
+/// Lowercases and splits on runs of non-alphanumeric characters. BM25 here
+/// only needs rough term overlap against policy text, not linguistic
+/// tokenization (stemming, stopwords, ...).
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Scores every chunk in `chunks` against `query` with BM25, returning one
+/// score per chunk in the same order. Term frequencies, document length,
+/// and the corpus-wide document frequency / average length are all derived
+/// from `chunks` itself - the chunks already loaded for this PDF form the
+/// whole BM25 corpus, so no separate index needs to be built or persisted.
+fn bm25_scores(chunks: &[String], query: &str) -> Vec<f32> {
+    let query_terms = tokenize(query);
+    if chunks.is_empty() || query_terms.is_empty() {
+        return vec![0.0; chunks.len()];
+    }
+
+    let doc_terms: Vec<Vec<String>> = chunks.iter().map(|c| tokenize(c)).collect();
+    let doc_lengths: Vec<usize> = doc_terms.iter().map(|terms| terms.len()).collect();
+    let n = chunks.len();
+    let avg_doc_length = doc_lengths.iter().sum::<usize>() as f32 / n as f32;
+
+    // Document frequency per distinct query term, over the whole corpus.
+    let mut doc_freq: HashMap<&str, usize> = HashMap::new();
+    for term in &query_terms {
+        doc_freq.entry(term.as_str()).or_insert_with(|| {
+            doc_terms.iter().filter(|terms| terms.iter().any(|t| t == term)).count()
+        });
+    }
+
+    doc_terms
+        .iter()
+        .zip(doc_lengths.iter())
+        .map(|(terms, &doc_len)| {
+            let mut term_counts: HashMap<&str, usize> = HashMap::new();
+            for t in terms {
+                *term_counts.entry(t.as_str()).or_insert(0) += 1;
+            }
+
+            query_terms
+                .iter()
+                .map(|term| {
+                    let df = *doc_freq.get(term.as_str()).unwrap_or(&0);
+                    if df == 0 {
+                        return 0.0;
+                    }
+                    let idf = ((n as f32 - df as f32 + 0.5) / (df as f32 + 0.5) + 1.0).ln();
+                    let tf = *term_counts.get(term.as_str()).unwrap_or(&0) as f32;
+                    let denom = tf + BM25_K1 * (1.0 - BM25_B + BM25_B * (doc_len as f32 / avg_doc_length));
+                    if denom == 0.0 { 0.0 } else { idf * (tf * (BM25_K1 + 1.0)) / denom }
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Converts a parallel slice of scores into 1-based ranks (rank 1 = the
+/// highest score), the form `reciprocal_rank_fusion` needs.
+fn ranks_from_scores(scores: &[f32]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut ranks = vec![0usize; scores.len()];
+    for (rank, idx) in order.into_iter().enumerate() {
+        ranks[idx] = rank + 1;
+    }
+    ranks
+}
+
+/// Fuses the vector-similarity ranking and the BM25 ranking into one score
+/// per chunk via Reciprocal Rank Fusion: `score = vector_weight / (k +
+/// vector_rank) + lexical_weight / (k + lexical_rank)`. Ranking (rather
+/// than raw score) is what's fused, so the two scales - cosine similarity
+/// in `[-1, 1]` and BM25's unbounded score - never need to be normalized
+/// against each other.
+fn reciprocal_rank_fusion(vector_ranks: &[usize], lexical_ranks: &[usize]) -> Vec<f32> {
+    let vw = vector_weight();
+    let lw = lexical_weight();
+    vector_ranks
+        .iter()
+        .zip(lexical_ranks.iter())
+        .map(|(&v_rank, &l_rank)| vw / (RRF_K + v_rank as f32) + lw / (RRF_K + l_rank as f32))
+        .collect()
+}
+
 /// Calculate cosine similarity between two vectors
+/// Cosine similarity between `vec1` and `vec2`, assuming both are already
+/// unit-length - true of every stored chunk embedding (normalized by
+/// `pack_embedding`) and of `query_embedding` once passed through
+/// `unit_vector` in `rank_chunks_with_index`. That lets this skip the magnitude
+/// division and just return the dot product.
 fn cosine_similarity(vec1: &[f32], vec2: &[f32]) -> f32 {
     if vec1.len() != vec2.len() {
         println!("Vector lengths do not match. Relevancy: 0%");
         return 0.0;
     }
 
-    let dot_product: f32 = vec1.iter().zip(vec2.iter()).map(|(a, b)| a * b).sum();
-    let magnitude1: f32 = vec1.iter().map(|v| v * v).sum::<f32>().sqrt();
-    let magnitude2: f32 = vec2.iter().map(|v| v * v).sum::<f32>().sqrt();
+    vec1.iter().zip(vec2.iter()).map(|(a, b)| a * b).sum()
+}
 
-    if magnitude1 == 0.0 || magnitude2 == 0.0 {
-        println!("One of the vectors has zero magnitude. Relevancy: 0%");
-        0.0
-    } else {
-        let relevancy = dot_product / (magnitude1 * magnitude2);
-        let percentage = (relevancy * 100.0).max(0.0); // Convert to percentage, ensure non-negative
-        println!("Content relevancy: {:.2}%", percentage);
-        relevancy
+
+/// Retrieval strategy for `rank_chunks`: pure vector similarity, or
+/// vector + BM25 fused with Reciprocal Rank Fusion. Mirrors the
+/// `"vector"|"hybrid"` values accepted by `POST /api/v1/search`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    Vector,
+    Hybrid,
+}
+
+impl Default for SearchMode {
+    fn default() -> Self {
+        SearchMode::Hybrid
+    }
+}
+
+/// Builds the HNSW index over `chunks`' embeddings. Callers that run
+/// several queries against the same chunk set (e.g. `/api/v1/search`'s
+/// per-query loop) should build this once up front and reuse it via
+/// `rank_chunks_with_index`, rather than paying insertion cost on every
+/// query through the `rank_chunks` convenience wrapper.
+pub fn build_ann_index(chunks: &[(String, Vec<f32>, i32, i32)]) -> HnswIndex {
+    let mut ann_index = HnswIndex::new();
+    for (i, (_, embedding, _, _)) in chunks.iter().enumerate() {
+        ann_index.insert(i, embedding.clone());
+    }
+    ann_index
+}
+
+/// The retrieval core shared by `rewrite_policy_with_context` (the
+/// existing file-based pipeline) and the `POST /api/v1/search` handler,
+/// so the two can't drift into scoring the same query differently.
+///
+/// Narrows `chunks` to an approximate-nearest-neighbor candidate pool via
+/// `ann_index`, unions it with the top lexical (BM25) hits over the
+/// *full* corpus so a chunk that's a rare-term exact match but
+/// vector-distant still gets a fair shot, scores that union per `mode`,
+/// and returns up to `top_k` results at or above `min_score` as `(score,
+/// chunk_text, start_offset, end_offset)` sorted best-first. In `Hybrid`
+/// mode `score` is the fused RRF score (small, unbounded); in `Vector`
+/// mode it's the raw cosine similarity (`[-1, 1]`) - `min_score` means
+/// different things in each mode, by design.
+pub async fn rank_chunks_with_index(
+    provider: &dyn EmbeddingProvider,
+    query: &str,
+    chunks: &[(String, Vec<f32>, i32, i32)],
+    ann_index: &HnswIndex,
+    mode: SearchMode,
+    top_k: usize,
+    min_score: f32,
+) -> Result<Vec<(f32, String, i32, i32)>> {
+    if chunks.is_empty() {
+        return Ok(Vec::new());
     }
+
+    let query_embedding = provider
+        .embed(&[query.to_string()])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("embedding provider returned no vector for the query"))?;
+    // Stored chunk embeddings are unit-normalized (see `pack_embedding`);
+    // normalize this one too so `cosine_similarity` can stay a plain dot
+    // product.
+    let query_embedding = unit_vector(&query_embedding);
+
+    let pool_size = ann_candidate_pool().max(top_k).min(chunks.len());
+    let neighbors = ann_index.search(&query_embedding, pool_size);
+
+    // An empty index (one built from no chunks) falls back to treating
+    // every chunk as a candidate.
+    let ann_candidates: Vec<usize> = if neighbors.is_empty() {
+        (0..chunks.len()).collect()
+    } else {
+        neighbors.into_iter().map(|(idx, _)| idx).collect()
+    };
+
+    // BM25 runs over every chunk, not just the ANN-narrowed pool - a
+    // chunk that's lexically an exact match (a rare clause/section ID)
+    // but semantically distant enough to miss the vector cut still needs
+    // a chance to surface, which it never would if BM25 only saw the
+    // chunks the vector search already picked.
+    let full_texts: Vec<String> = chunks.iter().map(|(text, ..)| text.clone()).collect();
+    let full_lexical_scores = bm25_scores(&full_texts, query);
+    let full_lexical_ranks = ranks_from_scores(&full_lexical_scores);
+
+    let candidate_indices: Vec<usize> = match mode {
+        SearchMode::Vector => ann_candidates,
+        SearchMode::Hybrid => {
+            let mut lexical_order: Vec<usize> = (0..chunks.len()).collect();
+            lexical_order.sort_by(|&a, &b| {
+                full_lexical_scores[b].partial_cmp(&full_lexical_scores[a]).unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            let mut union = ann_candidates;
+            for idx in lexical_order.into_iter().take(pool_size) {
+                if !union.contains(&idx) {
+                    union.push(idx);
+                }
+            }
+            union
+        }
+    };
+
+    let candidate_similarities: Vec<f32> = candidate_indices
+        .iter()
+        .map(|&i| cosine_similarity(&query_embedding, &chunks[i].1))
+        .collect();
+
+    let fused_scores: Vec<f32> = match mode {
+        SearchMode::Vector => candidate_similarities,
+        SearchMode::Hybrid => {
+            // Rank by cosine similarity (semantic) and separately by the
+            // full-corpus BM25 rank (lexical - catches exact policy
+            // numbers and clause IDs embeddings tend to blur), then fuse
+            // the two rankings with Reciprocal Rank Fusion rather than
+            // trusting either signal alone.
+            let vector_ranks = ranks_from_scores(&candidate_similarities);
+            let lexical_ranks: Vec<usize> = candidate_indices.iter().map(|&i| full_lexical_ranks[i]).collect();
+            reciprocal_rank_fusion(&vector_ranks, &lexical_ranks)
+        }
+    };
+
+    let mut ranked_local: Vec<usize> = (0..candidate_indices.len()).collect();
+    ranked_local.sort_by(|&a, &b| fused_scores[b].partial_cmp(&fused_scores[a]).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(ranked_local
+        .into_iter()
+        .filter(|&local_i| fused_scores[local_i] >= min_score)
+        .take(top_k)
+        .map(|local_i| {
+            let chunk_i = candidate_indices[local_i];
+            let (text, _, start_offset, end_offset) = &chunks[chunk_i];
+            (fused_scores[local_i], text.clone(), *start_offset, *end_offset)
+        })
+        .collect())
 }
 
+/// Convenience wrapper over `rank_chunks_with_index` for call sites that
+/// only run a single query against `chunks` (e.g.
+/// `rewrite_policy_with_context`, called once per document). Builds the
+/// HNSW index fresh each call - fine for one query, but callers looping
+/// over many queries against the same `chunks` (like `/api/v1/search`)
+/// should call `build_ann_index` once and use `rank_chunks_with_index`
+/// directly instead of paying insertion cost per query.
+pub async fn rank_chunks(
+    provider: &dyn EmbeddingProvider,
+    query: &str,
+    chunks: &[(String, Vec<f32>, i32, i32)],
+    mode: SearchMode,
+    top_k: usize,
+    min_score: f32,
+) -> Result<Vec<(f32, String, i32, i32)>> {
+    let ann_index = build_ann_index(chunks);
+    rank_chunks_with_index(provider, query, chunks, &ann_index, mode, top_k, min_score).await
+}
 
 pub async fn rewrite_policy_with_context(
-    api_key: &str,
+    provider: &dyn EmbeddingProvider,
     questions: &[String],
-    chunk_embeddings: &[(String, Vec<f32>)],
+    chunk_embeddings: &[(String, Vec<f32>, i32, i32)],
     pdf_filename: &str,
 
 ) -> Result<()> {
     // Combine all questions into a single text for embedding - this is already batched
     let combined_questions = questions.join(" ");
     println!("Getting combined embedding for all questions at once: {}", combined_questions);
-    
-    // Get a single embedding for all questions combined - this is one API call, not per question
-    let questions_embedding = get_single_embedding(&combined_questions, api_key).await?;
-    println!("Got questions embedding with {} dimensions", questions_embedding.len());
-    
-    // Use the passed chunk embeddings instead of computing them again
     println!("Using pre-computed chunk embeddings with {} chunks", chunk_embeddings.len());
-    
-    // Now find relevant chunks using the combined questions embedding
-    let mut chunk_similarities = Vec::new();
-    
-    for (chunk_text, chunk_emb) in chunk_embeddings {
-        let similarity = cosine_similarity(&questions_embedding, chunk_emb);
-        chunk_similarities.push((similarity, chunk_text.clone()));
-    }
-    
-    // Sort by similarity (highest first) and take top chunks
-    chunk_similarities.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
-    let top_chunks: Vec<String> = chunk_similarities
-        .into_iter()
-        .take(RELEVANT_CHUNKS)
-        .filter(|(similarity, _)| *similarity > 0.6) // Lower threshold since we're combining questions
-        .map(|(_, text)| text)
-        .collect();
-    
+
+    let ranked = rank_chunks(provider, &combined_questions, chunk_embeddings, SearchMode::Hybrid, RELEVANT_CHUNKS, f32::NEG_INFINITY).await?;
+
     let mut new_content = String::new();
 
-    
     // Add relevant context
-    if !top_chunks.is_empty() {
-        let context = top_chunks.join("\n\n---\n\n");
+    if !ranked.is_empty() {
+        let context = ranked.iter().map(|(_, text, _, _)| text.as_str()).collect::<Vec<_>>().join("\n\n---\n\n");
         new_content.push_str(&context);
         new_content.push_str("\n\n");
     } else {
         new_content.push_str("No highly relevant context found for these questions.\n\n");
     }
-    
+
     let context_filename = format!("pdfs/{}_contextfiltered.txt", pdf_filename);
     let context_path = Path::new(&context_filename);
     fs::write(context_path, new_content)?;
     println!("Successfully wrote relevant context to {}", context_filename);
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_lowercases_and_splits_on_non_alphanumeric() {
+        assert_eq!(tokenize("Clause 12.3, Section-B!"), vec!["clause", "12", "3", "section", "b"]);
+        assert_eq!(tokenize(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn bm25_scores_favors_exact_rare_term_match_over_unrelated_chunks() {
+        let chunks = vec![
+            "the policy excludes pre-existing conditions".to_string(),
+            "clause 42b covers dental procedures".to_string(),
+            "unrelated text about something else entirely".to_string(),
+        ];
+        let scores = bm25_scores(&chunks, "clause 42b");
+        assert_eq!(scores.len(), 3);
+        assert!(scores[1] > scores[0]);
+        assert!(scores[1] > scores[2]);
+    }
+
+    #[test]
+    fn bm25_scores_empty_query_or_corpus_returns_zeros() {
+        let chunks = vec!["some text".to_string()];
+        assert_eq!(bm25_scores(&chunks, ""), vec![0.0]);
+        assert_eq!(bm25_scores(&[], "query"), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn reciprocal_rank_fusion_rewards_agreement_between_rankings() {
+        // Chunk 0 is top-ranked on both signals; chunk 1 is worst on both.
+        let vector_ranks = vec![1, 3, 2];
+        let lexical_ranks = vec![1, 3, 2];
+        let fused = reciprocal_rank_fusion(&vector_ranks, &lexical_ranks);
+        assert!(fused[0] > fused[2]);
+        assert!(fused[2] > fused[1]);
+    }
+
+    #[test]
+    fn ranks_from_scores_assigns_rank_1_to_the_highest_score() {
+        assert_eq!(ranks_from_scores(&[0.2, 0.9, 0.5]), vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn chunk_text_structured_preserves_full_text_via_offsets() {
+        let text = "First sentence here. Second one follows.\n\nA new paragraph starts now.";
+        let chunks = chunk_text_structured(text, 1000, 1, 10_000);
+        assert!(!chunks.is_empty());
+        for chunk in &chunks {
+            let chars: Vec<char> = text.chars().collect();
+            let expected: String = chars[chunk.start_offset..chunk.end_offset].iter().collect();
+            assert_eq!(chunk.text, expected);
+        }
+    }
+
+    #[test]
+    fn chunk_text_structured_empty_input_yields_no_chunks() {
+        assert!(chunk_text_structured("", 1000, 1, 10_000).is_empty());
+    }
+
+    #[test]
+    fn extraction_scope_fingerprint_is_none_for_default_opts() {
+        assert_eq!(extraction_scope_fingerprint(false, None), None);
+    }
+
+    #[test]
+    fn extraction_scope_fingerprint_differs_by_password_and_page_range() {
+        let password_only = extraction_scope_fingerprint(true, None).unwrap();
+        let page_range_only = extraction_scope_fingerprint(false, Some((1, 10))).unwrap();
+        let both = extraction_scope_fingerprint(true, Some((1, 10))).unwrap();
+        let different_range = extraction_scope_fingerprint(false, Some((11, 20))).unwrap();
+
+        assert_ne!(password_only, page_range_only);
+        assert_ne!(password_only, both);
+        assert_ne!(page_range_only, different_range);
+    }
+}
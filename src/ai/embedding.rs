@@ -0,0 +1,49 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// A pluggable embedding backend for chunk/query vectorization. Each
+/// implementation owns its own request shape, batch-size limit, and output
+/// dimensionality; the rest of the crate just holds a `Box<dyn
+/// EmbeddingProvider>` and calls `embed`, so swapping models - or running
+/// fully offline against a local Ollama server - never touches the call
+/// site. Mirrors `backend::TransformerBackend`'s shape for the completions
+/// side of the pipeline.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embeds `texts` in one batched request, returning one vector per
+    /// input in the same order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// The length of the vectors this provider returns, so callers (cosine
+    /// similarity, DB column sizing) don't have to hardcode a model's
+    /// dimensionality.
+    fn dimensions(&self) -> usize;
+
+    /// An upper bound on how many tokens a single batched `embed` call can
+    /// carry, so `get_policy_chunk_embeddings` can size batches per
+    /// provider instead of a single hardcoded payload-byte check.
+    fn max_batch_tokens(&self) -> usize;
+
+    /// The model name, stored alongside each embedding row so switching
+    /// providers/models later doesn't silently mix incompatible vectors in
+    /// the same similarity search.
+    fn model_name(&self) -> &str;
+}
+
+/// A rough token estimate (no tokenizer is available here) used to size
+/// batches against `EmbeddingProvider::max_batch_tokens` - OpenAI's own
+/// rule of thumb of ~4 characters per token for English text.
+pub(crate) fn approx_tokens(text: &str) -> usize {
+    text.len() / 4
+}
+
+/// Builds the provider selected by the `EMBEDDING_PROVIDER` env var
+/// (`gemini` (default), `openai`, `ollama`), matching how
+/// `backend::default_backend` selects `TRANSFORMER_BACKEND`.
+pub fn default_embedding_provider() -> Box<dyn EmbeddingProvider> {
+    match std::env::var("EMBEDDING_PROVIDER").unwrap_or_default().to_lowercase().as_str() {
+        "openai" => Box::new(super::openai::OpenAiEmbeddingProvider::from_env()),
+        "ollama" => Box::new(super::ollama::OllamaEmbeddingProvider::from_env()),
+        _ => Box::new(super::gemini::GeminiEmbeddingProvider::from_env()),
+    }
+}
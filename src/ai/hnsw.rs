@@ -0,0 +1,274 @@
+//! In-memory HNSW (Hierarchical Navigable Small World) index over
+//! embedding vectors.
+//!
+//! `ai::embed::rewrite_policy_with_context` used to rank every stored
+//! chunk against the question embedding with a linear `cosine_similarity`
+//! scan - fine for one PDF's worth of chunks, but O(N) work that grows
+//! with the corpus. This index keeps a multi-layer neighbor graph: search
+//! enters at the top layer's entry point, greedily hops to the closest
+//! neighbor at each layer, and descends, finishing with a bounded
+//! candidate search at the base layer. That makes a `search` call
+//! roughly logarithmic in the number of indexed vectors rather than
+//! linear.
+//!
+//! Vectors are normalized to unit length on insert, so cosine similarity
+//! reduces to a plain dot product everywhere below. The index is built in
+//! memory from the stored embeddings rather than persisted to disk -
+//! simpler than keeping a serialized graph in sync with `pdf_embeddings`.
+//! Construction is the only expensive operation, so callers that run
+//! multiple queries against the same chunk set (e.g. `/api/v1/search`'s
+//! per-query loop) build one `HnswIndex` up front and reuse it across
+//! queries rather than rebuilding per query - see
+//! `ai::embed::build_ann_index` / `rank_chunks_with_index`.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Bidirectional links kept per layer for each node. Larger `M` gives
+/// better recall at the cost of slower inserts and more memory; override
+/// with `HNSW_M`.
+fn hnsw_m() -> usize {
+    std::env::var("HNSW_M").ok().and_then(|v| v.parse().ok()).unwrap_or(16)
+}
+
+/// Size of the dynamic candidate list explored at the base layer during
+/// search. Higher `ef_search` trades latency for recall; override with
+/// `HNSW_EF_SEARCH`.
+fn hnsw_ef_search() -> usize {
+    std::env::var("HNSW_EF_SEARCH").ok().and_then(|v| v.parse().ok()).unwrap_or(64)
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        vector.to_vec()
+    } else {
+        vector.iter().map(|x| x / norm).collect()
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// A (similarity, node index) pair ordered by similarity, so a
+/// `BinaryHeap<Scored>` pops the most-similar entry first.
+#[derive(Copy, Clone)]
+struct Scored(f32, usize);
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl Eq for Scored {}
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Node {
+    vector: Vec<f32>,
+    /// `neighbors[layer]` holds this node's links at that layer; the
+    /// node exists in layers `0..neighbors.len()`.
+    neighbors: Vec<Vec<usize>>,
+}
+
+/// An HNSW index over caller-supplied `(id, vector)` pairs. `id` is
+/// opaque to the index - callers use it to map a search hit back to
+/// whatever it represents (here, a position in a chunk list).
+pub struct HnswIndex {
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    entry_point: Option<usize>,
+    nodes: Vec<Node>,
+    ids: Vec<usize>,
+    rng_state: u64,
+}
+
+impl HnswIndex {
+    pub fn new() -> Self {
+        let m = hnsw_m();
+        let ef_search = hnsw_ef_search();
+        Self {
+            m,
+            ef_construction: ef_search.max(m * 2),
+            ef_search,
+            entry_point: None,
+            nodes: Vec::new(),
+            ids: Vec::new(),
+            // Fixed seed: recall quality doesn't depend on unpredictability,
+            // and a fixed seed keeps index construction reproducible.
+            rng_state: 0x9E3779B97F4A7C15,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// xorshift64 - enough randomness for level assignment without
+    /// pulling in a `rand` dependency for one call site.
+    fn next_unit_f64(&mut self) -> f64 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        ((self.rng_state >> 11) as f64 / (1u64 << 53) as f64).max(f64::MIN_POSITIVE)
+    }
+
+    fn random_level(&mut self) -> usize {
+        let level_mult = 1.0 / (self.m as f64).ln();
+        let r = self.next_unit_f64();
+        (-r.ln() * level_mult).floor() as usize
+    }
+
+    /// Insert one vector under `id`. `id` need not be unique from the
+    /// index's point of view, but callers should keep it unique for
+    /// `search` results to make sense.
+    pub fn insert(&mut self, id: usize, vector: Vec<f32>) {
+        let vector = normalize(&vector);
+        let level = self.random_level();
+        let node_idx = self.nodes.len();
+        self.nodes.push(Node { vector: vector.clone(), neighbors: vec![Vec::new(); level + 1] });
+        self.ids.push(id);
+
+        let entry = match self.entry_point {
+            None => {
+                self.entry_point = Some(node_idx);
+                return;
+            }
+            Some(e) => e,
+        };
+
+        let top_layer = self.nodes[entry].neighbors.len() - 1;
+        let mut ep = entry;
+
+        // Greedy descent: above the new node's own level, only move to
+        // the single closest neighbor at each layer.
+        for layer in (level + 1..=top_layer).rev() {
+            if let Some(&(closest, _)) = self.search_layer(&vector, &[ep], 1, layer).first() {
+                ep = closest;
+            }
+        }
+
+        // From the new node's level down to 0, do a proper
+        // ef_construction search and connect to the M nearest found.
+        for layer in (0..=level.min(top_layer)).rev() {
+            let candidates = self.search_layer(&vector, &[ep], self.ef_construction, layer);
+            let selected: Vec<(usize, f32)> = candidates.into_iter().take(self.m).collect();
+            for &(neighbor_idx, _) in &selected {
+                self.connect(node_idx, neighbor_idx, layer);
+                self.connect(neighbor_idx, node_idx, layer);
+                self.prune_neighbors(neighbor_idx, layer);
+            }
+            if let Some(&(closest, _)) = selected.first() {
+                ep = closest;
+            }
+        }
+
+        if level > top_layer {
+            self.entry_point = Some(node_idx);
+        }
+    }
+
+    fn connect(&mut self, from: usize, to: usize, layer: usize) {
+        if layer < self.nodes[from].neighbors.len() && !self.nodes[from].neighbors[layer].contains(&to) {
+            self.nodes[from].neighbors[layer].push(to);
+        }
+    }
+
+    fn prune_neighbors(&mut self, node_idx: usize, layer: usize) {
+        let m = self.m;
+        if self.nodes[node_idx].neighbors[layer].len() <= m {
+            return;
+        }
+        let vector = self.nodes[node_idx].vector.clone();
+        let mut scored: Vec<(usize, f32)> = self.nodes[node_idx].neighbors[layer]
+            .iter()
+            .map(|&n| (n, dot(&vector, &self.nodes[n].vector)))
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        scored.truncate(m);
+        self.nodes[node_idx].neighbors[layer] = scored.into_iter().map(|(n, _)| n).collect();
+    }
+
+    /// Greedy/bounded search for up to `ef` nearest neighbors of `query`
+    /// among nodes reachable from `entry_points` at `layer`, returned as
+    /// `(node index, similarity)` sorted best-first.
+    fn search_layer(&self, query: &[f32], entry_points: &[usize], ef: usize, layer: usize) -> Vec<(usize, f32)> {
+        let mut visited: HashSet<usize> = entry_points.iter().copied().collect();
+        let mut candidates: BinaryHeap<Scored> = BinaryHeap::new();
+        let mut found: Vec<Scored> = Vec::new();
+
+        for &ep in entry_points {
+            let sim = dot(query, &self.nodes[ep].vector);
+            candidates.push(Scored(sim, ep));
+            found.push(Scored(sim, ep));
+        }
+
+        while let Some(Scored(sim, current)) = candidates.pop() {
+            let worst = found.iter().map(|s| s.0).fold(f32::INFINITY, f32::min);
+            if found.len() >= ef && sim < worst {
+                break;
+            }
+
+            let Some(layer_neighbors) = self.nodes[current].neighbors.get(layer) else {
+                continue;
+            };
+            for &neighbor in layer_neighbors {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let neighbor_sim = dot(query, &self.nodes[neighbor].vector);
+                if found.len() < ef {
+                    candidates.push(Scored(neighbor_sim, neighbor));
+                    found.push(Scored(neighbor_sim, neighbor));
+                } else {
+                    let worst = found.iter().map(|s| s.0).fold(f32::INFINITY, f32::min);
+                    if neighbor_sim > worst {
+                        candidates.push(Scored(neighbor_sim, neighbor));
+                        found.push(Scored(neighbor_sim, neighbor));
+                        if let Some(pos) = found.iter().enumerate().min_by(|a, b| a.1 .0.partial_cmp(&b.1 .0).unwrap_or(Ordering::Equal)).map(|(i, _)| i) {
+                            found.remove(pos);
+                        }
+                    }
+                }
+            }
+        }
+
+        found.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        found.into_iter().map(|Scored(sim, idx)| (idx, sim)).collect()
+    }
+
+    /// Approximate top-`k` nearest neighbors of `query` by cosine
+    /// similarity, returned as `(id, similarity)` sorted best-first.
+    /// Returns an empty vec if the index has no vectors yet - callers
+    /// should fall back to a linear scan in that case.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(usize, f32)> {
+        let Some(entry) = self.entry_point else {
+            return Vec::new();
+        };
+        let query = normalize(query);
+        let top_layer = self.nodes[entry].neighbors.len() - 1;
+
+        let mut ep = entry;
+        for layer in (1..=top_layer).rev() {
+            if let Some(&(closest, _)) = self.search_layer(&query, &[ep], 1, layer).first() {
+                ep = closest;
+            }
+        }
+
+        self.search_layer(&query, &[ep], self.ef_search.max(k), 0)
+            .into_iter()
+            .take(k)
+            .map(|(idx, sim)| (self.ids[idx], sim))
+            .collect()
+    }
+}
@@ -0,0 +1,172 @@
+use std::env;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::backend::{parse_answers, rate_from_env, sanitize_policy, system_instruction, user_prompt, RateLimiter, TransformerBackend};
+use super::embedding::EmbeddingProvider;
+
+/// Shared across every `OllamaBackend` instance so `OLLAMA_MAX_REQUESTS_PER_SECOND`
+/// caps total throughput, not just per-request.
+static RATE_LIMITER: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new(rate_from_env("OLLAMA_MAX_REQUESTS_PER_SECOND")));
+
+/// Talks to a local (or self-hosted) Ollama server's `/api/generate`
+/// endpoint, selected with `TRANSFORMER_BACKEND=ollama`. Unlike the hosted
+/// providers this needs no API key - just a reachable `OLLAMA_HOST`.
+pub struct OllamaBackend {
+    host: String,
+    model: String,
+}
+
+impl OllamaBackend {
+    pub fn from_env() -> Self {
+        Self {
+            host: env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://localhost:11434".to_string()),
+            model: env::var("OLLAMA_MODEL").unwrap_or_else(|_| "llama3".to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GenerateRequest<'a> {
+    model: &'a str,
+    prompt: String,
+    system: String,
+    format: &'static str,
+    stream: bool,
+}
+
+#[async_trait]
+impl TransformerBackend for OllamaBackend {
+    async fn answer(&self, questions: &[String], context: &str) -> Result<Vec<String>> {
+        let prompt = user_prompt(questions, &sanitize_policy(context));
+        let body = GenerateRequest {
+            model: &self.model,
+            prompt,
+            system: system_instruction(),
+            format: "json",
+            stream: false,
+        };
+
+        RATE_LIMITER.wait().await;
+
+        let client = Client::new();
+        let response = client
+            .post(format!("{}/api/generate", self.host))
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let raw_text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(anyhow!("Ollama API request failed: {} - {}", status, raw_text));
+        }
+
+        let json: Value = serde_json::from_str(&raw_text)
+            .map_err(|e| anyhow!("Error deserializing Ollama response: {}\nRaw response: {}", e, raw_text))?;
+
+        let text = json
+            .get("response")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| anyhow!("Ollama response missing \"response\" field"))?;
+
+        Ok(parse_answers(text))
+    }
+}
+
+/// Shared across every `OllamaEmbeddingProvider` instance, separate from
+/// the chat `RATE_LIMITER` above in case the two are tuned differently.
+static EMBEDDING_RATE_LIMITER: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new(rate_from_env("OLLAMA_EMBEDDING_MAX_REQUESTS_PER_SECOND")));
+
+/// Talks to a local (or self-hosted) Ollama server's `/api/embed`
+/// endpoint, selected with `EMBEDDING_PROVIDER=ollama`. Like
+/// `OllamaBackend`, this needs no API key - just a reachable
+/// `OLLAMA_HOST` - which is what makes a fully offline deployment possible.
+pub struct OllamaEmbeddingProvider {
+    host: String,
+    model: String,
+    dimensions: usize,
+}
+
+impl OllamaEmbeddingProvider {
+    pub fn from_env() -> Self {
+        Self {
+            host: env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://localhost:11434".to_string()),
+            model: env::var("OLLAMA_EMBEDDING_MODEL").unwrap_or_else(|_| "nomic-embed-text".to_string()),
+            dimensions: env::var("OLLAMA_EMBEDDING_DIMENSIONS").ok().and_then(|v| v.parse().ok()).unwrap_or(768),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddingProvider {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let body = EmbedRequest {
+            model: &self.model,
+            input: texts,
+        };
+
+        EMBEDDING_RATE_LIMITER.wait().await;
+
+        let client = Client::new();
+        let response = client
+            .post(format!("{}/api/embed", self.host))
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let raw_text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(anyhow!("Ollama embeddings request failed: {} - {}", status, raw_text));
+        }
+
+        let json: Value = serde_json::from_str(&raw_text)
+            .map_err(|e| anyhow!("Error deserializing Ollama embeddings response: {}\nRaw response: {}", e, raw_text))?;
+
+        let embeddings = json
+            .get("embeddings")
+            .and_then(|e| e.as_array())
+            .ok_or_else(|| anyhow!("Ollama embeddings response missing \"embeddings\" array"))?;
+
+        embeddings
+            .iter()
+            .map(|vec| {
+                vec.as_array()
+                    .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+                    .ok_or_else(|| anyhow!("Ollama embeddings response entry is not an array"))
+            })
+            .collect()
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        // Local models vary widely in context size; default conservatively
+        // and let `OLLAMA_EMBEDDING_MAX_BATCH_TOKENS` raise it for models
+        // known to support more.
+        std::env::var("OLLAMA_EMBEDDING_MAX_BATCH_TOKENS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2000)
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
@@ -0,0 +1,204 @@
+use std::env;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::backend::{parse_answers, rate_from_env, sanitize_policy, system_instruction, user_prompt, RateLimiter, TransformerBackend};
+use super::embedding::EmbeddingProvider;
+
+/// Shared across every `OpenAiBackend` instance so `OPENAI_MAX_REQUESTS_PER_SECOND`
+/// caps total throughput, not just per-request.
+static RATE_LIMITER: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new(rate_from_env("OPENAI_MAX_REQUESTS_PER_SECOND")));
+
+/// Talks to any OpenAI-compatible `/chat/completions` endpoint (OpenAI
+/// itself, or a self-hosted proxy that mirrors its API), selected with
+/// `TRANSFORMER_BACKEND=openai`. `OPENAI_BASE_URL` lets this point at a
+/// compatible third-party host instead of `api.openai.com`.
+pub struct OpenAiBackend {
+    api_key: String,
+    base_url: String,
+    model: String,
+}
+
+impl OpenAiBackend {
+    pub fn from_env() -> Self {
+        Self {
+            api_key: env::var("OPENAI_API_KEY").unwrap_or_default(),
+            base_url: env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+            model: env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-4o-mini".to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage>,
+    response_format: ResponseFormat,
+}
+
+#[derive(Serialize)]
+struct ChatMessage {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[async_trait]
+impl TransformerBackend for OpenAiBackend {
+    async fn answer(&self, questions: &[String], context: &str) -> Result<Vec<String>> {
+        if self.api_key.is_empty() {
+            return Err(anyhow!("OPENAI_API_KEY not found in env"));
+        }
+
+        // OpenAI's `json_object` response format refuses a bare top-level
+        // array, so ask for `{"answers": [...]}` instead - `parse_answers`
+        // already knows how to unwrap that shape.
+        let mut system = system_instruction();
+        system.push_str("\nReturn a JSON object of the form {\"answers\": [...]}, not a bare array.\n");
+        let prompt = user_prompt(questions, &sanitize_policy(context));
+
+        let body = ChatRequest {
+            model: &self.model,
+            messages: vec![
+                ChatMessage { role: "system", content: system },
+                ChatMessage { role: "user", content: prompt },
+            ],
+            response_format: ResponseFormat { kind: "json_object" },
+        };
+
+        RATE_LIMITER.wait().await;
+
+        let client = Client::new();
+        let response = client
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let raw_text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(anyhow!("OpenAI-compatible API request failed: {} - {}", status, raw_text));
+        }
+
+        let json: Value = serde_json::from_str(&raw_text)
+            .map_err(|e| anyhow!("Error deserializing OpenAI response: {}\nRaw response: {}", e, raw_text))?;
+
+        let content = json
+            .get("choices")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("message"))
+            .and_then(|m| m.get("content"))
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| anyhow!("OpenAI response missing choices[0].message.content"))?;
+
+        Ok(parse_answers(content))
+    }
+}
+
+/// Shared across every `OpenAiEmbeddingProvider` instance, separate from
+/// the chat `RATE_LIMITER` above since `/embeddings` has its own quota.
+static EMBEDDING_RATE_LIMITER: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new(rate_from_env("OPENAI_EMBEDDING_MAX_REQUESTS_PER_SECOND")));
+
+/// Talks to any OpenAI-compatible `/embeddings` endpoint, selected with
+/// `EMBEDDING_PROVIDER=openai`. `OPENAI_BASE_URL` is reused so a
+/// compatible proxy only needs to be configured once for both chat and
+/// embeddings.
+pub struct OpenAiEmbeddingProvider {
+    api_key: String,
+    base_url: String,
+    model: String,
+    dimensions: usize,
+}
+
+impl OpenAiEmbeddingProvider {
+    pub fn from_env() -> Self {
+        Self {
+            api_key: env::var("OPENAI_API_KEY").unwrap_or_default(),
+            base_url: env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+            model: env::var("OPENAI_EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string()),
+            dimensions: env::var("OPENAI_EMBEDDING_DIMENSIONS").ok().and_then(|v| v.parse().ok()).unwrap_or(1536),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbeddingProvider {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if self.api_key.is_empty() {
+            return Err(anyhow!("OPENAI_API_KEY not found in env"));
+        }
+
+        let body = EmbeddingsRequest {
+            model: &self.model,
+            input: texts,
+        };
+
+        EMBEDDING_RATE_LIMITER.wait().await;
+
+        let client = Client::new();
+        let response = client
+            .post(format!("{}/embeddings", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let raw_text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(anyhow!("OpenAI-compatible embeddings request failed: {} - {}", status, raw_text));
+        }
+
+        let json: Value = serde_json::from_str(&raw_text)
+            .map_err(|e| anyhow!("Error deserializing OpenAI embeddings response: {}\nRaw response: {}", e, raw_text))?;
+
+        let data = json
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| anyhow!("OpenAI embeddings response missing \"data\" array"))?;
+
+        data.iter()
+            .map(|entry| {
+                entry
+                    .get("embedding")
+                    .and_then(|e| e.as_array())
+                    .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+                    .ok_or_else(|| anyhow!("OpenAI embeddings response entry missing \"embedding\" array"))
+            })
+            .collect()
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        // `text-embedding-3-*` accepts up to 8191 tokens per input item
+        // across a batch; leave headroom rather than sitting on the limit.
+        8000
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Application Default Credentials service-account key file, as written by
+/// `gcloud iam service-accounts keys create`.
+#[derive(Deserialize)]
+struct AdcServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+#[derive(Serialize)]
+struct Claims {
+    iss: String,
+    scope: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+/// Fetches and caches short-lived OAuth access tokens for Vertex AI,
+/// exchanging a service-account ADC key for a bearer token the same way
+/// `gcloud`/the Google client libraries do: a self-signed JWT assertion
+/// traded for an access token at `token_uri`. The token is cached in
+/// memory alongside its expiry and refreshed once it's within 60 seconds
+/// of expiring, so most calls to `token()` are free.
+pub struct AccessTokenProvider {
+    service_account: AdcServiceAccount,
+    cached: Mutex<Option<(String, i64)>>, // (token, expires_at_unix)
+}
+
+impl AccessTokenProvider {
+    pub fn from_adc_file(path: &str) -> Result<Self> {
+        let raw = fs::read_to_string(path).map_err(|e| anyhow!("failed to read ADC file {}: {}", path, e))?;
+        let service_account: AdcServiceAccount =
+            serde_json::from_str(&raw).map_err(|e| anyhow!("failed to parse ADC file {}: {}", path, e))?;
+        Ok(Self {
+            service_account,
+            cached: Mutex::new(None),
+        })
+    }
+
+    pub async fn token(&self) -> Result<String> {
+        let now = Utc::now().timestamp();
+
+        if let Some((token, expires_at)) = self.cached.lock().unwrap().as_ref() {
+            if *expires_at - now > 60 {
+                return Ok(token.clone());
+            }
+        }
+
+        let (token, expires_at) = self.fetch_token(now).await?;
+        *self.cached.lock().unwrap() = Some((token.clone(), expires_at));
+        Ok(token)
+    }
+
+    async fn fetch_token(&self, now: i64) -> Result<(String, i64)> {
+        let claims = Claims {
+            iss: self.service_account.client_email.clone(),
+            scope: "https://www.googleapis.com/auth/cloud-platform".to_string(),
+            aud: self.service_account.token_uri.clone(),
+            iat: now,
+            exp: now + 3600,
+        };
+
+        let key = EncodingKey::from_rsa_pem(self.service_account.private_key.as_bytes())
+            .map_err(|e| anyhow!("invalid ADC private key: {}", e))?;
+        let assertion = encode(&Header::new(Algorithm::RS256), &claims, &key)
+            .map_err(|e| anyhow!("failed to sign JWT assertion: {}", e))?;
+
+        let client = Client::new();
+        let response = client
+            .post(&self.service_account.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+
+        let status = response.status();
+        let raw_text = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Vertex AI token exchange failed: {} - {}", status, raw_text));
+        }
+
+        let parsed: TokenResponse = serde_json::from_str(&raw_text)
+            .map_err(|e| anyhow!("Error deserializing token response: {}\nRaw response: {}", e, raw_text))?;
+
+        Ok((parsed.access_token, now + parsed.expires_in))
+    }
+}
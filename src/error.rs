@@ -0,0 +1,67 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use thiserror::Error;
+
+/// Crate-wide error type for the ingestion/answering pipeline.
+///
+/// Each variant is either *fatal* (the request as a whole cannot continue,
+/// e.g. we couldn't even download the document) or *recoverable* (the
+/// pipeline can skip the failing piece and keep going, e.g. one slide's OCR
+/// or one question's answer). `is_fatal` reports that classification.
+/// `run_pipeline` follows it at each call site - it bails on the fatal
+/// variants and keeps going past `Extraction`/`Gemini` failures to produce
+/// a partial `AnswersResponse` - rather than branching on `is_fatal`.
+#[derive(Debug, Error)]
+pub enum HackError {
+    #[error("failed to download document: {0}")]
+    Download(String),
+
+    #[error("unsupported file type: {0}")]
+    Unsupported(String),
+
+    #[error("failed to extract text: {0}")]
+    Extraction(String),
+
+    #[error("failed to compute embeddings: {0}")]
+    Embedding(String),
+
+    #[error("gemini request failed: {0}")]
+    Gemini(String),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl HackError {
+    /// Whether this error should abort the whole request, as opposed to
+    /// being skipped and reported alongside whatever partial results we do
+    /// have.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            HackError::Download(_) => true,
+            HackError::Unsupported(_) => true,
+            HackError::Extraction(_) => false,
+            HackError::Embedding(_) => true,
+            HackError::Gemini(_) => false,
+            HackError::Io(_) => true,
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            HackError::Unsupported(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for HackError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let message = self.to_string();
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
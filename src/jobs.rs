@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex, Semaphore};
+use uuid::Uuid;
+
+use crate::server::{run_pipeline, QuestionRequest};
+
+/// State of a single ingestion/answering job, as returned by
+/// `GET /hackrx/jobs/{id}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status")]
+pub enum JobState {
+    Pending,
+    Running,
+    Done { answers: Vec<String> },
+    Failed { error: String },
+}
+
+/// A small in-memory job queue fronting the (slow) document pipeline so
+/// `POST /hackrx/run` can return immediately with a `job_id` instead of
+/// holding the connection open for the full download/OCR/embed/answer run.
+///
+/// Concurrency is bounded by a semaphore so that N submitted documents
+/// don't all hit ImageMagick/OCR at once.
+#[derive(Clone)]
+pub struct JobQueue {
+    jobs: Arc<Mutex<HashMap<Uuid, JobState>>>,
+    tx: mpsc::UnboundedSender<(Uuid, QuestionRequest)>,
+}
+
+impl JobQueue {
+    /// Spawns the background worker and returns a handle to the queue.
+    /// `max_concurrent_jobs` bounds how many pipelines run at once.
+    pub fn spawn(max_concurrent_jobs: usize) -> Self {
+        let jobs: Arc<Mutex<HashMap<Uuid, JobState>>> = Arc::new(Mutex::new(HashMap::new()));
+        let (tx, mut rx) = mpsc::unbounded_channel::<(Uuid, QuestionRequest)>();
+        let semaphore = Arc::new(Semaphore::new(max_concurrent_jobs));
+
+        let worker_jobs = Arc::clone(&jobs);
+        tokio::spawn(async move {
+            while let Some((job_id, body)) = rx.recv().await {
+                let jobs = Arc::clone(&worker_jobs);
+                let semaphore = Arc::clone(&semaphore);
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await.expect("semaphore closed");
+
+                    jobs.lock().await.insert(job_id, JobState::Running);
+
+                    let state = match run_pipeline(body).await {
+                        Ok(response) => JobState::Done { answers: response.answers },
+                        Err(e) => JobState::Failed { error: e.to_string() },
+                    };
+
+                    jobs.lock().await.insert(job_id, state);
+                });
+            }
+        });
+
+        Self { jobs, tx }
+    }
+
+    /// Enqueues a job and returns its id. The job starts out `Pending`
+    /// until a worker slot picks it up.
+    pub async fn submit(&self, body: QuestionRequest) -> Uuid {
+        let job_id = Uuid::new_v4();
+        self.jobs.lock().await.insert(job_id, JobState::Pending);
+        // The receiver only goes away if the worker task panicked, which we
+        // treat as unrecoverable for the process.
+        self.tx.send((job_id, body)).expect("job worker task died");
+        job_id
+    }
+
+    pub async fn get(&self, job_id: &Uuid) -> Option<JobState> {
+        self.jobs.lock().await.get(job_id).cloned()
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::pdf::StdError;
+
+/// Resource-policy knobs for the conversion/OCR pipeline. All of these are
+/// configurable via env vars so operators can tune them per-deployment
+/// without a rebuild, with defaults that protect the host from a
+/// decompression-bomb-style upload.
+pub fn max_download_bytes() -> u64 {
+    env_or("MAX_DOWNLOAD_BYTES", 100 * 1024 * 1024) // 100 MB
+}
+
+pub fn max_pages() -> usize {
+    env_or("MAX_PAGES", 200) as usize
+}
+
+pub fn max_dimension_px() -> u32 {
+    env_or("MAX_DIMENSION_PX", 6000) as u32
+}
+
+pub fn subprocess_timeout() -> Duration {
+    Duration::from_secs(env_or("SUBPROCESS_TIMEOUT_SECS", 120))
+}
+
+pub fn pptx_dpi() -> u32 {
+    env_or("PPTX_CONVERT_DPI", 150) as u32
+}
+
+pub fn pdf_render_dpi() -> u32 {
+    env_or("PDF_RENDER_DPI", 300) as u32
+}
+
+pub fn image_quality() -> u32 {
+    env_or("IMAGE_CONVERT_QUALITY", 85) as u32
+}
+
+/// How many links deep the recursive URL loader (`pdf::load_url`) follows
+/// by default when a caller doesn't specify a depth explicitly.
+pub fn url_crawl_depth() -> usize {
+    env_or("URL_CRAWL_DEPTH", 1) as usize
+}
+
+/// Hard cap on total pages fetched by the recursive URL loader per
+/// request, regardless of depth/branching factor, so a densely linked
+/// site can't turn one request into an unbounded crawl.
+pub fn url_crawl_max_pages() -> usize {
+    env_or("URL_CRAWL_MAX_PAGES", 20) as usize
+}
+
+/// Below this many characters of `pdf_extract` text per page, a PDF chunk
+/// is assumed to be scanned/image-only and is re-processed through OCR
+/// (see `pdf::process_pdf_chunk`) instead of returning near-empty text.
+pub fn ocr_fallback_min_chars_per_page() -> usize {
+    env_or("OCR_FALLBACK_MIN_CHARS_PER_PAGE", 20) as usize
+}
+
+fn env_or(key: &str, default: u64) -> u64 {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// Runs `command` to completion, killing it if it runs longer than
+/// `timeout`. Subprocesses here (`convert`, `soffice`, `pdftoppm`, `pdftk`,
+/// `qpdf`, `ocrs`) have no built-in time limit, so a pathological input can
+/// otherwise hang a worker indefinitely.
+pub fn run_with_timeout(command: &mut Command, timeout: Duration) -> Result<ExitStatus, Box<StdError>> {
+    let mut child: Child = command.spawn()?;
+    let start = Instant::now();
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("subprocess exceeded timeout of {:?} and was killed", timeout).into());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Like `run_with_timeout`, but also captures stdout - for subprocesses
+/// whose output is the extracted text itself (external document loaders)
+/// rather than a file written as a side effect. Stdout is drained on a
+/// background thread while the timeout is polled, so a command that
+/// writes more than a pipe buffer's worth of output can't deadlock against
+/// the busy-wait loop below.
+pub fn run_with_timeout_capturing_stdout(command: &mut Command, timeout: Duration) -> Result<(ExitStatus, Vec<u8>), Box<StdError>> {
+    command.stdout(Stdio::piped());
+    let mut child: Child = command.spawn()?;
+    let mut stdout = child.stdout.take().expect("stdout was piped");
+    let reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        buf
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("subprocess exceeded timeout of {:?} and was killed", timeout).into());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    let stdout_bytes = reader.join().map_err(|_| "stdout reader thread panicked")?;
+    Ok((status, stdout_bytes))
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::pdf::StdError;
+
+/// Maps a file extension to an external command template used to extract
+/// its text instead of the crate's built-in conversion path, e.g.
+/// `"docx": "pandoc --to plain $1"` or `"xlsx": "ssconvert $1 $2"`. `$1` is
+/// replaced with the (quoted) input path; `$2`, if present in the
+/// template, with a quoted temp output path whose contents are read back
+/// once the command exits - otherwise the command's stdout is captured
+/// directly.
+#[derive(Deserialize, Default)]
+pub struct LoaderRegistry {
+    commands: HashMap<String, String>,
+}
+
+/// Loaded once from `LOADER_CONFIG_FILE` (a JSON object of
+/// `{"ext": "command template"}`) if set, otherwise empty - every
+/// extension then falls back to `extract_file_text_sync`'s built-in path,
+/// matching how the rest of the crate reads optional config from the
+/// environment (see `limits.rs`).
+pub static REGISTRY: Lazy<Arc<LoaderRegistry>> = Lazy::new(|| Arc::new(LoaderRegistry::from_env()));
+
+impl LoaderRegistry {
+    pub fn from_env() -> Self {
+        let Ok(path) = env::var("LOADER_CONFIG_FILE") else {
+            return Self::default();
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(raw) => match serde_json::from_str(&raw) {
+                Ok(commands) => Self { commands },
+                Err(e) => {
+                    println!("Warning: failed to parse loader config {}: {} (using built-in extraction only)", path, e);
+                    Self::default()
+                }
+            },
+            Err(e) => {
+                println!("Warning: failed to read loader config {}: {} (using built-in extraction only)", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// The configured command template for `ext`, if any.
+    pub fn command_for(&self, ext: &str) -> Option<&str> {
+        self.commands.get(ext).map(|s| s.as_str())
+    }
+}
+
+/// Runs the loader command configured for `ext` against `file_path`, if
+/// one is in `registry`. Returns `Ok(None)` when no loader is configured,
+/// so `extract_file_text_sync` falls back to its built-in per-format path.
+pub fn run_configured_loader(registry: &LoaderRegistry, ext: &str, file_path: &str) -> Result<Option<String>, Box<StdError>> {
+    let Some(template) = registry.command_for(ext) else {
+        return Ok(None);
+    };
+
+    let timeout = crate::limits::subprocess_timeout();
+    let quoted_input = shell_quote(file_path);
+
+    let text = if template.contains("$2") {
+        let output_path = env::temp_dir().join(format!("loader_out_{}.txt", Uuid::new_v4()));
+        let expanded = template
+            .replace("$1", &quoted_input)
+            .replace("$2", &shell_quote(&output_path.to_string_lossy()));
+
+        let status = crate::limits::run_with_timeout(Command::new("sh").arg("-c").arg(&expanded), timeout)?;
+        if !status.success() {
+            let _ = fs::remove_file(&output_path);
+            return Err(format!("loader command for .{} exited with {}", ext, status).into());
+        }
+
+        let text = fs::read_to_string(&output_path)
+            .map_err(|e| format!("loader command for .{} produced no readable output: {}", ext, e))?;
+        let _ = fs::remove_file(&output_path);
+        text
+    } else {
+        let expanded = template.replace("$1", &quoted_input);
+        let (status, stdout) =
+            crate::limits::run_with_timeout_capturing_stdout(Command::new("sh").arg("-c").arg(&expanded), timeout)?;
+        if !status.success() {
+            return Err(format!("loader command for .{} exited with {}", ext, status).into());
+        }
+        String::from_utf8_lossy(&stdout).into_owned()
+    };
+
+    Ok(Some(text))
+}
+
+/// Wraps `path` in single quotes for safe interpolation into a `sh -c`
+/// command string, escaping any embedded single quote the POSIX-shell way
+/// (`'...'"'"'...'`).
+fn shell_quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r#"'"'"'"#))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_quote_wraps_plain_path_in_single_quotes() {
+        assert_eq!(shell_quote("/tmp/my file.pdf"), "'/tmp/my file.pdf'");
+    }
+
+    #[test]
+    fn shell_quote_escapes_embedded_single_quotes() {
+        // A naively single-quoted `it's.docx` would let the shell see the
+        // quote close early - this is exactly the injection shell_quote
+        // exists to prevent.
+        assert_eq!(shell_quote("it's.docx"), r#"'it'"'"'s.docx'"#);
+    }
+
+    #[test]
+    fn shell_quote_handles_empty_path() {
+        assert_eq!(shell_quote(""), "''");
+    }
+}
@@ -3,11 +3,18 @@ mod pdf;
 mod ai;
 mod ocr;
 mod final_challenge;
+mod error;
+mod sniff;
+mod jobs;
+mod store;
+mod limits;
+mod loaders;
 
 use axum::{
-    routing::post,
+    routing::{get, post},
     Router,
 };
+use jobs::JobQueue;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tracing_subscriber;
@@ -34,8 +41,17 @@ async fn main() -> anyhow::Result<()> {
             "1" => {
                 println!("Starting server on http://0.0.0.0:8000 ... Press Ctrl+C to stop.");
 
-                // Spawn server task with a shutdown signal for clean exit
-                let app = Router::new().route("/api/v1/hackrx/run", post(server::hackrx_run));
+                // Spawn server task with a shutdown signal for clean exit.
+                // The job queue bounds how many documents get OCR'd/converted
+                // at once (default: one per CPU core) regardless of how many
+                // requests are enqueued.
+                let job_queue = JobQueue::spawn(num_cpus::get());
+                let app = Router::new()
+                    .route("/api/v1/hackrx/run", post(server::hackrx_run))
+                    .route("/api/v1/hackrx/jobs/:id", get(server::get_job))
+                    .route("/api/v1/search", post(server::search))
+                    .route("/api/v1/hackrx/stream", post(server::hackrx_stream))
+                    .with_state(job_queue);
                 let addr: SocketAddr = "0.0.0.0:8000".parse()?;
                 let listener = TcpListener::bind(addr).await?;
 
@@ -1,46 +1,79 @@
 use std::path::Path;
 use std::fs;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 use crate::pdf::StdError;
 
-pub fn extract_text_from_pptx(pptx_path: &str) -> Result<String, Box<StdError>> {
+/// Number of slides OCR'd concurrently, overridable via `OCR_CONCURRENCY`.
+/// Defaults to the CPU count, since `ocrs` is itself CPU-bound.
+fn ocr_concurrency() -> usize {
+    std::env::var("OCR_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(num_cpus::get)
+}
+
+pub async fn extract_text_from_pptx(pptx_path: &str) -> Result<String, Box<StdError>> {
     println!("Processing PPTX file: {}", pptx_path);
-    
+
     // Create directory for extracted images
     let images_dir = Path::new("temp_pptx_images");
     if images_dir.exists() {
         fs::remove_dir_all(images_dir)?;
     }
     fs::create_dir_all(images_dir)?;
-    
-    // Extract all pages from PPTX as images
-    let image_paths = extract_pptx_pages_as_images(pptx_path, images_dir)?;
-    
-    // Apply OCR to each extracted image using ocrs CLI
-    let mut all_text = Vec::new();
-    
-    for (page_num, image_path) in image_paths.iter().enumerate() {
-        println!("Processing PPTX page {}: {}", page_num + 1, image_path);
-        
-        // Extract text from this page image using ocrs CLI tool
-        match extract_text_with_ocrs(image_path) {
-            Ok(page_text) => {
+
+    // Extract all pages from PPTX as images. This shells out to
+    // ImageMagick/LibreOffice, so run it off the async runtime's worker
+    // threads.
+    let image_paths = {
+        let pptx_path = pptx_path.to_string();
+        let images_dir = images_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || extract_pptx_pages_as_images(&pptx_path, &images_dir)).await??
+    };
+
+    // OCR every slide concurrently, bounded by a semaphore so a 60-slide
+    // deck doesn't spawn 60 `ocrs` processes at once. Each slide already
+    // writes to a uniquely-named `slide-NN.png`/`.txt` pair, so concurrent
+    // writes don't collide.
+    let semaphore = Arc::new(Semaphore::new(ocr_concurrency()));
+    let mut tasks = Vec::with_capacity(image_paths.len());
+    for (page_num, image_path) in image_paths.into_iter().enumerate() {
+        let semaphore = Arc::clone(&semaphore);
+        tasks.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await.expect("ocr semaphore closed");
+            let result = tokio::task::spawn_blocking(move || extract_text_with_ocrs(&image_path)).await;
+            (page_num, result)
+        }));
+    }
+
+    // Preserve slide order by page_num regardless of completion order.
+    let mut paged_text: Vec<Option<String>> = vec![None; tasks.len()];
+    for task in tasks {
+        let (page_num, result) = task.await?;
+        match result {
+            Ok(Ok(page_text)) => {
                 if !page_text.trim().is_empty() {
-                    all_text.push(format!("=== Slide {} ===\n{}", page_num + 1, page_text));
+                    paged_text[page_num] = Some(format!("=== Slide {} ===\n{}", page_num + 1, page_text));
                 }
             }
-            Err(e) => {
+            Ok(Err(e)) => {
                 println!("Warning: Failed to extract text from slide {}: {}", page_num + 1, e);
                 // Continue processing other slides even if one fails
             }
+            Err(e) => {
+                println!("Warning: OCR task for slide {} panicked: {}", page_num + 1, e);
+            }
         }
     }
-    
+
     // Clean up temporary images
     let _ = fs::remove_dir_all(images_dir);
-    
+
     // Combine all slide text
-    let combined_text = all_text.join("\n\n");
-    
+    let slide_count = paged_text.iter().filter(|t| t.is_some()).count();
+    let combined_text = paged_text.into_iter().flatten().collect::<Vec<_>>().join("\n\n");
+
     if combined_text.trim().is_empty() {
         return Err("No text could be extracted from the PPTX file".into());
     }
@@ -66,7 +99,7 @@ pub fn extract_text_from_pptx(pptx_path: &str) -> Result<String, Box<StdError>>
         .map_err(|e| format!("Failed to write PPTX text to file {:?}: {}", txt_path, e))?;
     
     println!("PPTX extracted text saved to: {:?}", txt_path);
-    println!("Successfully extracted text from {} slides", all_text.len());
+    println!("Successfully extracted text from {} slides", slide_count);
     
     Ok(combined_text)
 }
@@ -117,21 +150,25 @@ pub fn extract_text_with_ocrs(image_path: &str) -> Result<String, Box<StdError>>
 
 fn extract_pptx_pages_as_images(pptx_path: &str, output_dir: &Path) -> Result<Vec<String>, Box<StdError>> {
     println!("Extracting PPTX pages as images using ImageMagick...");
-    
-    // Use 'convert' command for ImageMagick v6
-    let status = std::process::Command::new("convert")
-        .arg("-density")
-        .arg("150")  // 150 DPI for good OCR quality while being faster
-        .arg("-background")
-        .arg("white")
-        .arg("-alpha")
-        .arg("remove")
-        .arg("-quality")
-        .arg("85")   // Good quality, faster processing
-        .arg(pptx_path)
-        .arg(output_dir.join("slide-%02d.png").to_str().unwrap())
-        .status()
-        .map_err(|e| format!("Failed to execute convert command: {}", e))?;
+
+    // DPI/quality and the subprocess timeout are configurable resource-policy
+    // knobs (see `crate::limits`) rather than hard-coded, so a deployment
+    // can tune them without a rebuild.
+    let status = crate::limits::run_with_timeout(
+        std::process::Command::new("convert")
+            .arg("-density")
+            .arg(crate::limits::pptx_dpi().to_string())
+            .arg("-background")
+            .arg("white")
+            .arg("-alpha")
+            .arg("remove")
+            .arg("-quality")
+            .arg(crate::limits::image_quality().to_string())
+            .arg(pptx_path)
+            .arg(output_dir.join("slide-%02d.png").to_str().unwrap()),
+        crate::limits::subprocess_timeout(),
+    )
+    .map_err(|e| format!("Failed to execute convert command: {}", e))?;
 
     if !status.success() {
         println!("ImageMagick direct conversion failed, falling back to LibreOffice method");
@@ -140,7 +177,40 @@ fn extract_pptx_pages_as_images(pptx_path: &str, output_dir: &Path) -> Result<Ve
     }
 
     println!("ImageMagick conversion successful");
-    collect_image_files(output_dir)
+    let image_paths = collect_image_files(output_dir)?;
+    enforce_page_and_dimension_caps(&image_paths)?;
+    Ok(image_paths)
+}
+
+/// Rejects the conversion outright if it produced more pages/slides than
+/// `max_pages()`, or any image whose width/height exceeds
+/// `max_dimension_px()` - both are decompression-bomb-style guardrails
+/// against a huge or maliciously crafted upload.
+fn enforce_page_and_dimension_caps(image_paths: &[String]) -> Result<(), Box<StdError>> {
+    let max_pages = crate::limits::max_pages();
+    if image_paths.len() > max_pages {
+        return Err(format!(
+            "document has {} pages/slides, exceeding the {}-page limit",
+            image_paths.len(),
+            max_pages
+        )
+        .into());
+    }
+
+    let max_dimension = crate::limits::max_dimension_px();
+    for image_path in image_paths {
+        if let Ok((width, height)) = image::image_dimensions(image_path) {
+            if width > max_dimension || height > max_dimension {
+                return Err(format!(
+                    "page image {} is {}x{}, exceeding the {}px dimension cap",
+                    image_path, width, height, max_dimension
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(())
 }
 
 // Add these helper functions
@@ -183,16 +253,18 @@ pub fn convert_pptx_to_pdf_for_images(pptx_path: &str) -> Result<String, Box<Std
     let pdf_path = temp_dir.join(format!("{}.pdf", base_name));
     
     // Use LibreOffice to convert PPTX to PDF
-    let status = std::process::Command::new("soffice")
-        .arg("--headless")
-        .arg("--convert-to")
-        .arg("pdf")
-        .arg("--outdir")
-        .arg(temp_dir)
-        .arg(pptx_path)
-        .status()
-        .map_err(|e| format!("Failed to execute LibreOffice: {}. Make sure LibreOffice is installed.", e))?;
-    
+    let status = crate::limits::run_with_timeout(
+        std::process::Command::new("soffice")
+            .arg("--headless")
+            .arg("--convert-to")
+            .arg("pdf")
+            .arg("--outdir")
+            .arg(temp_dir)
+            .arg(pptx_path),
+        crate::limits::subprocess_timeout(),
+    )
+    .map_err(|e| format!("Failed to execute LibreOffice: {}. Make sure LibreOffice is installed.", e))?;
+
     if !status.success() {
         return Err("LibreOffice PPTX to PDF conversion failed".into());
     }
@@ -202,23 +274,25 @@ pub fn convert_pptx_to_pdf_for_images(pptx_path: &str) -> Result<String, Box<Std
 
 pub fn convert_pdf_pages_to_images(pdf_path: &str, output_dir: &Path) -> Result<Vec<String>, Box<StdError>> {
     // Use pdftoppm to convert PDF pages to images
-    let status = std::process::Command::new("pdftoppm")
-        .arg("-png")
-        .arg("-r")
-        .arg("300") // 300 DPI for good OCR quality
-        .arg(pdf_path)
-        .arg(output_dir.join("slide").to_str().unwrap())
-        .status()
-        .map_err(|e| format!("Failed to execute pdftoppm: {}. Make sure poppler-utils is installed.", e))?;
-    
+    let status = crate::limits::run_with_timeout(
+        std::process::Command::new("pdftoppm")
+            .arg("-png")
+            .arg("-r")
+            .arg(crate::limits::pdf_render_dpi().to_string())
+            .arg(pdf_path)
+            .arg(output_dir.join("slide").to_str().unwrap()),
+        crate::limits::subprocess_timeout(),
+    )
+    .map_err(|e| format!("Failed to execute pdftoppm: {}. Make sure poppler-utils is installed.", e))?;
+
     if !status.success() {
         return Err("PDF to images conversion failed".into());
     }
-    
+
     // Collect all generated image files
     let mut image_paths = Vec::new();
     let entries = fs::read_dir(output_dir)?;
-    
+
     for entry in entries {
         let entry = entry?;
         let path = entry.path();
@@ -226,9 +300,11 @@ pub fn convert_pdf_pages_to_images(pdf_path: &str, output_dir: &Path) -> Result<
             image_paths.push(path.to_string_lossy().to_string());
         }
     }
-    
+
     // Sort by filename to maintain slide order
     image_paths.sort();
-    
+
+    enforce_page_and_dimension_caps(&image_paths)?;
+
     Ok(image_paths)
 }
@@ -9,14 +9,54 @@ use docx_rs::*;
 use calamine::{open_workbook_auto, Reader, DataType, Range};
 use printpdf::*;
 use std::io::BufWriter;
+use std::io::Read;
+use sha2::{Digest, Sha256};
 
-use crate::ocr::extract_text_from_pptx;
+pub type StdError = dyn std::error::Error + Send + Sync + 'static;
 
+/// Bumped whenever a change to the extraction/conversion logic below would
+/// make a previously-cached `{hash}_{CACHE_VERSION}.txt`/`.pdf` artifact
+/// stale, so old entries are naturally orphaned instead of silently reused.
+const CACHE_VERSION: &str = "v1";
+
+/// Streams `path` to compute its SHA-256 digest, used to key the
+/// extraction/conversion caches on content rather than filename - so two
+/// different uploads that happen to share a name (or an edited file
+/// reusing one) never collide or return stale text.
+fn content_hash_of_file(path: &str) -> Result<String, Box<StdError>> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
+/// The cache-key stem derived from a content hash - distinct from the hash
+/// itself so bumping `CACHE_VERSION` invalidates every existing entry
+/// without touching how the hash is computed.
+fn cache_key_for(hash: &str) -> String {
+    format!("{}_{}", hash, CACHE_VERSION)
+}
 
-pub type StdError = dyn std::error::Error + Send + Sync + 'static;
+/// Records `original_path -> cache_key` next to the cached artifact, purely
+/// for diagnosability (so `pdfs/<hash>_v1.txt` can be traced back to the
+/// upload that produced it).
+fn write_source_sidecar(pdfs_dir: &Path, cache_key: &str, original_path: &str) -> Result<(), Box<StdError>> {
+    let sidecar_path = pdfs_dir.join(format!("{}.src", cache_key));
+    fs::write(sidecar_path, original_path)?;
+    Ok(())
+}
 
-pub async fn download_file(url: &str, file_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// Downloads `url` to `file_path`, returning the response's `Content-Type`
+/// header (if any) so callers can sniff the real file type rather than
+/// trusting the URL's extension.
+pub async fn download_file(url: &str, file_path: &str) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
     // Allowed extensions to download
     let allowed_exts = ["jpeg", "pptx", "docx", "xlsx", "png", "pdf"];
     // Extensions to ignore
@@ -25,37 +65,233 @@ pub async fn download_file(url: &str, file_path: &str) -> Result<(), Box<dyn std
     // Parse URL to extract the path component (without query parameters)
     let parsed_url = url::Url::parse(url)?;
     let path = parsed_url.path();
-    
+
     // Extract filename from path
     let filename = path.split('/').last().unwrap_or("");
 
-    
-    
-    // Extract the file extension from filename (not the full URL)
-    let ext = filename.rsplit('.').next().unwrap_or("").to_lowercase();
-    println!("Extension is: {}", ext);
+    // A URL's last path segment doesn't always have a dot (e.g.
+    // `.../get-secret-token`) - `rsplit('.').next()` would otherwise
+    // return the whole segment and get rejected as an "unsupported
+    // extension" before the file is even fetched. Only gate on the
+    // extension when the URL actually has one; an extension-less URL
+    // gets downloaded and left to `sniff_extension` (run by the caller
+    // against the real bytes) to classify.
+    if let Some(ext) = filename.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()) {
+        println!("Extension is: {}", ext);
+
+        // Check if extension is to be ignored
+        if ignore_exts.contains(&ext.as_str()) {
+            // Skip downloading the file
+            println!("Ignoring download for file with extension: {}", ext);
+            return Ok(None);
+        }
 
-    // Check if extension is to be ignored
-    if ignore_exts.contains(&ext.as_str()) {
-        // Skip downloading the file
-        println!("Ignoring download for file with extension: {}", ext);
-        return Ok(());
+        // Check if extension is allowed
+        if !allowed_exts.contains(&ext.as_str()) {
+            // Return error for unsupported file extension
+            return Err(format!("Download not supported for files with .{} extension", ext).into());
+        }
     }
 
-    // Check if extension is allowed
-    if !allowed_exts.contains(&ext.as_str()) {
-        // Return error for unsupported file extension
-        return Err(format!("Download not supported for files with .{} extension", ext).into());
+    // If allowed (or no extension to judge by), proceed to download
+    let response = reqwest::get(url).await?;
+
+    let max_bytes = crate::limits::max_download_bytes();
+    if let Some(len) = response.content_length() {
+        if len > max_bytes {
+            return Err(format!(
+                "document too large: {} bytes exceeds the {} byte limit",
+                len, max_bytes
+            )
+            .into());
+        }
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let bytes = response.bytes().await?;
+
+    // A server can omit or lie about Content-Length, so also enforce the
+    // cap against what we actually received.
+    if bytes.len() as u64 > max_bytes {
+        return Err(format!(
+            "document too large: {} bytes exceeds the {} byte limit",
+            bytes.len(),
+            max_bytes
+        )
+        .into());
     }
 
-    // If allowed, proceed to download
-    let bytes = reqwest::get(url).await?.bytes().await?;
     async_fs::write(file_path, &bytes).await?;
 
-    Ok(())
+    Ok(content_type)
+}
+
+/// Crawls from `start_url` up to `depth` links deep, concatenating the
+/// extracted plain text of every page visited - turns the crate into a
+/// source for website knowledge bases, not just uploaded files. Pages are
+/// visited breadth-first so a shallow depth still covers the site's most
+/// prominent links first; a normalized-URL visited set prevents cycles,
+/// and `crate::limits::url_crawl_max_pages` bounds the total fetched
+/// regardless of depth or branching factor.
+pub async fn load_url(start_url: &str, depth: usize, same_host_only: bool) -> Result<String, Box<StdError>> {
+    let start = url::Url::parse(start_url)?;
+    let max_pages = crate::limits::url_crawl_max_pages();
+
+    let mut visited: std::collections::HashSet<url::Url> = std::collections::HashSet::new();
+    let mut queue: std::collections::VecDeque<(url::Url, usize)> = std::collections::VecDeque::new();
+    queue.push_back((start.clone(), depth));
+
+    let client = reqwest::Client::new();
+    let mut pages_text = Vec::new();
+
+    while let Some((url, remaining_depth)) = queue.pop_front() {
+        if visited.contains(&url) || visited.len() >= max_pages {
+            continue;
+        }
+        visited.insert(url.clone());
+
+        let response = match client.get(url.clone()).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                println!("Warning: failed to fetch {}: {}, skipping", url, e);
+                continue;
+            }
+        };
+
+        if !response.status().is_success() {
+            println!("Warning: {} returned {}, skipping", url, response.status());
+            continue;
+        }
+
+        let html = match response.text().await {
+            Ok(html) => html,
+            Err(e) => {
+                println!("Warning: failed to read body of {}: {}, skipping", url, e);
+                continue;
+            }
+        };
+
+        pages_text.push(format!("=== {} ===\n{}", url, html_to_text(&html)));
+
+        if remaining_depth == 0 {
+            continue;
+        }
+
+        for link in extract_links(&html, &url) {
+            if same_host_only && link.host_str() != start.host_str() {
+                continue;
+            }
+            if !visited.contains(&link) {
+                queue.push_back((link, remaining_depth - 1));
+            }
+        }
+    }
+
+    Ok(pages_text.join("\n\n"))
+}
+
+/// Peeks at `url` to decide whether it actually serves HTML, rather than
+/// trusting an extension-less path alone (a PDF/DOCX/image served from a
+/// CDN link with no file extension is exactly the case `sniff_extension`
+/// exists for - it must not get routed into `load_url` and lossily
+/// UTF-8-decoded as "crawled text"). Checks the `Content-Type` header
+/// first, falling back to a magic-byte peek at the body so a server that
+/// omits or lies about `Content-Type` doesn't get misclassified either
+/// way.
+pub async fn looks_like_html(url: &str) -> Result<bool, Box<StdError>> {
+    let response = reqwest::get(url).await?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_lowercase());
+
+    if let Some(ct) = &content_type {
+        if ct.contains("text/html") {
+            return Ok(true);
+        }
+        // An explicit non-HTML content type (application/pdf, image/png,
+        // ...) is conclusive without needing to look at the body.
+        if ct.starts_with("application/") || ct.starts_with("image/") {
+            return Ok(false);
+        }
+    }
+
+    let bytes = response.bytes().await?;
+    let header = &bytes[..bytes.len().min(512)];
+    if header.starts_with(b"%PDF")
+        || header.starts_with(b"\x89PNG")
+        || header.starts_with(b"\xFF\xD8\xFF")
+        || header.starts_with(b"PK\x03\x04")
+    {
+        return Ok(false);
+    }
+
+    // No conclusive binary signature and no contradicting Content-Type -
+    // treat it as the HTML/text page it looks like.
+    Ok(true)
+}
+
+/// Resolves every `<a href="...">` in `html` against `base`, skipping
+/// anchors that don't resolve to an absolute `http(s)` URL (mailto:,
+/// javascript:, in-page fragments, etc).
+fn extract_links(html: &str, base: &url::Url) -> Vec<url::Url> {
+    let href_re = regex::Regex::new(r#"(?is)<a\s+[^>]*href\s*=\s*["']([^"']+)["']"#).unwrap();
+    href_re
+        .captures_iter(html)
+        .filter_map(|caps| base.join(&caps[1]).ok())
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+        .collect()
+}
+
+/// Strips script/style/nav boilerplate and remaining HTML tags, leaving
+/// plain text - a lightweight stand-in for a full html-to-markdown
+/// conversion, good enough for feeding a page's body into the Q&A
+/// pipeline.
+fn html_to_text(html: &str) -> String {
+    let boilerplate_re = regex::Regex::new(r"(?is)<(script|style|nav|noscript)[^>]*>.*?</\1>").unwrap();
+    let without_boilerplate = boilerplate_re.replace_all(html, " ");
+
+    let tag_re = regex::Regex::new(r"(?is)<[^>]+>").unwrap();
+    let untagged = tag_re.replace_all(&without_boilerplate, " ");
+
+    let decoded = untagged
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+
+    decoded
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
 }
 
-fn extract_file_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
+/// Optional owner/user password and page-range window for PDF extraction.
+/// Threaded through `extract_pdf_text_sync`, `get_pdf_page_count_accurate`,
+/// `process_pdf_chunk`, and `split_pdf_chunk` so encrypted documents and
+/// single-chapter extracts from a large manual both work without a
+/// separate code path. DOCX/XLSX are converted to PDF first, so the
+/// options apply to the converted PDF's pages; the image, text and PPTX
+/// paths and any configured external loader ignore them.
+#[derive(Clone, Default)]
+pub struct PdfExtractOpts {
+    pub password: Option<String>,
+    /// Inclusive `(first_page, last_page)` window, 1-indexed. `None` means
+    /// the whole document.
+    pub page_range: Option<(usize, usize)>,
+}
+
+fn extract_file_text_sync(file_path: &str, opts: &PdfExtractOpts) -> Result<String, Box<StdError>> {
     // Determine file extension
     let ext = Path::new(file_path)
         .extension()
@@ -63,32 +299,39 @@ fn extract_file_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
         .unwrap_or("")
         .to_lowercase();
 
+    // A configured external loader (see `loaders.rs`) takes priority over
+    // the built-in path below, so operators can plug in a higher-fidelity
+    // converter (e.g. pandoc for DOCX) or add a new format by config alone.
+    if let Some(text) = crate::loaders::run_configured_loader(&crate::loaders::REGISTRY, &ext, file_path)? {
+        return Ok(text);
+    }
+
     // Handle different file types
     match ext.as_str() {
         "docx" => {
-            // Convert DOCX to PDF first, then extract text
-            let pdf_path = convert_docx_to_pdf(file_path)?;
-            extract_pdf_text_sync(&pdf_path)
+            // Convert DOCX to PDF first, then extract text. The cache key
+            // below is the original DOCX's hash, not the converted PDF's,
+            // so an identical upload still hits the cache even if PDF
+            // generation isn't perfectly deterministic run-to-run.
+            let (pdf_path, source_hash) = convert_docx_to_pdf(file_path)?;
+            extract_pdf_text_sync(&pdf_path, &source_hash, opts)
         }
         "xlsx" => {
-            // Convert XLSX to PDF first, then extract text
-            let pdf_path = convert_xlsx_to_pdf(file_path)?;
-            extract_pdf_text_sync(&pdf_path)
+            // Convert XLSX to PDF first, then extract text (same
+            // source-hash-keyed caching as the DOCX branch above).
+            let (pdf_path, source_hash) = convert_xlsx_to_pdf(file_path)?;
+            extract_pdf_text_sync(&pdf_path, &source_hash, opts)
         }
         "pdf" => {
             // Extract directly from PDF
-            extract_pdf_text_sync(file_path)
+            let source_hash = content_hash_of_file(file_path)?;
+            extract_pdf_text_sync(file_path, &source_hash, opts)
         }
         "jpeg" | "png" => {
             // Extract text directly using OCR from images
             crate::ocr::extract_text_with_ocrs(file_path)
 
         }
-        "pptx" => {
-            // Extract PPTX pages as images first, then apply OCR
-            extract_text_from_pptx(file_path)
-        }
-
         "txt" => {
             extract_token_from_text(file_path)
         }
@@ -99,21 +342,24 @@ fn extract_file_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
 }
 
 
-// Rename your existing function to avoid conflicts
-fn extract_pdf_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
+/// Extracts text from `file_path` (a PDF), caching the result under a key
+/// derived from `source_hash` - the hash of the *original* upload, which
+/// for DOCX/XLSX callers differs from `file_path`'s own bytes (see
+/// `convert_docx_to_pdf`/`convert_xlsx_to_pdf`).
+fn extract_pdf_text_sync(file_path: &str, source_hash: &str, opts: &PdfExtractOpts) -> Result<String, Box<StdError>> {
     // Ensure output dir
     let pdfs_dir = Path::new("pdfs");
     if !pdfs_dir.exists() {
         fs::create_dir_all(pdfs_dir)?;
     }
 
-    // Generate output filename based on input PDF filename
-    let pdf_filename = Path::new(file_path)
-        .file_stem()
-        .and_then(|name| name.to_str())
-        .unwrap_or("document");
-    
-    let txt_filename = format!("{}.txt", pdf_filename);
+    // A password or page range yields different text from the same bytes,
+    // so a scoped extraction gets its own cache entry.
+    let cache_key = match crate::ai::embed::extraction_scope_fingerprint(opts.password.is_some(), opts.page_range) {
+        Some(scope) => format!("{}_{}", cache_key_for(source_hash), scope),
+        None => cache_key_for(source_hash),
+    };
+    let txt_filename = format!("{}.txt", cache_key);
     let txt_path = pdfs_dir.join(&txt_filename);
 
     // Check if text file already exists
@@ -129,6 +375,7 @@ fn extract_pdf_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
     }
 
     println!("Text file not found, extracting from PDF...");
+    write_source_sidecar(pdfs_dir, &cache_key, file_path)?;
 
     // Create temp directory for PDF chunks
     let temp_dir = pdfs_dir.join("temp_chunks");
@@ -137,19 +384,43 @@ fn extract_pdf_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
     }
     fs::create_dir_all(&temp_dir)?;
 
-    // Get total number of pages using pdftk or similar tool
-    let total_pages = get_pdf_page_count_accurate(file_path)?;
+    // Get total number of pages using pdftk or similar tool, then clamp to
+    // the requested page window (if any) before the ceiling-division
+    // chunking below, so only the requested pages are split and OCR'd.
+    let document_pages = get_pdf_page_count_accurate(file_path, opts.password.as_deref())?;
+    let (first_page, last_page) = match opts.page_range {
+        Some((first, last)) => (first.max(1), last.min(document_pages)),
+        None => (1, document_pages),
+    };
+    if first_page > last_page {
+        return Err(format!(
+            "page range {}-{} is empty or out of bounds for a {}-page document",
+            first_page, last_page, document_pages
+        )
+        .into());
+    }
+    let total_pages = last_page - first_page + 1;
+
+    let max_pages = crate::limits::max_pages();
+    if total_pages > max_pages {
+        return Err(format!(
+            "document has {} pages, exceeding the {}-page limit",
+            total_pages, max_pages
+        )
+        .into());
+    }
 
     // Get number of available CPU cores
     let num_cores = num_cpus::get();
     let pages_per_chunk = (total_pages + num_cores - 1) / num_cores; // Ceiling division
     println!("Total pages: {}, CPU cores: {}, pages per chunk: {}", total_pages, num_cores, pages_per_chunk);
 
-    // Create page ranges for all available cores
+    // Create page ranges for all available cores, offset into the
+    // requested window.
     let page_ranges: Vec<(usize, usize)> = (0..num_cores)
         .map(|i| {
-            let start = i * pages_per_chunk + 1; // PDF pages are 1-indexed
-            let end = ((i + 1) * pages_per_chunk).min(total_pages);
+            let start = first_page + i * pages_per_chunk; // PDF pages are 1-indexed
+            let end = (first_page + (i + 1) * pages_per_chunk - 1).min(last_page);
             (start, end)
         })
         .filter(|(start, end)| start <= end)
@@ -160,14 +431,16 @@ fn extract_pdf_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
     // Split PDF into chunks and process in parallel
     let file_path = Arc::new(file_path.to_string());
     let temp_dir = Arc::new(temp_dir);
-    
+    let password = Arc::new(opts.password.clone());
+
     let chunk_results: Result<Vec<String>, Box<StdError>> = page_ranges
         .into_par_iter()
         .enumerate()
         .map(|(chunk_idx, (start_page, end_page))| {
             let file_path = Arc::clone(&file_path);
             let temp_dir = Arc::clone(&temp_dir);
-            process_pdf_chunk(&file_path, &temp_dir, start_page, end_page, chunk_idx)
+            let password = Arc::clone(&password);
+            process_pdf_chunk(&file_path, &temp_dir, start_page, end_page, chunk_idx, password.as_deref())
         })
         .collect();
 
@@ -194,13 +467,14 @@ fn extract_pdf_text_sync(file_path: &str) -> Result<String, Box<StdError>> {
     Ok(cleaned_text)
 }
 
-fn get_pdf_page_count_accurate(file_path: &str) -> Result<usize, Box<StdError>> {
+fn get_pdf_page_count_accurate(file_path: &str, password: Option<&str>) -> Result<usize, Box<StdError>> {
     // Try using pdftk first (most accurate)
-    if let Ok(output) = Command::new("pdftk")
-        .arg(file_path)
-        .arg("dump_data")
-        .output()
-    {
+    let mut pdftk_cmd = Command::new("pdftk");
+    pdftk_cmd.arg(file_path);
+    if let Some(password) = password {
+        pdftk_cmd.arg("input_pw").arg(password);
+    }
+    if let Ok(output) = pdftk_cmd.arg("dump_data").output() {
         let output_str = String::from_utf8_lossy(&output.stdout);
         for line in output_str.lines() {
             if line.starts_with("NumberOfPages:") {
@@ -212,10 +486,11 @@ fn get_pdf_page_count_accurate(file_path: &str) -> Result<usize, Box<StdError>>
     }
 
     // Fallback: try using pdfinfo
-    if let Ok(output) = Command::new("pdfinfo")
-        .arg(file_path)
-        .output()
-    {
+    let mut pdfinfo_cmd = Command::new("pdfinfo");
+    if let Some(password) = password {
+        pdfinfo_cmd.arg("-upw").arg(password);
+    }
+    if let Ok(output) = pdfinfo_cmd.arg(file_path).output() {
         let output_str = String::from_utf8_lossy(&output.stdout);
         for line in output_str.lines() {
             if line.starts_with("Pages:") {
@@ -239,58 +514,125 @@ fn process_pdf_chunk(
     start_page: usize,
     end_page: usize,
     chunk_idx: usize,
+    password: Option<&str>,
 ) -> Result<String, Box<StdError>> {
     println!("Processing chunk {} (pages {}-{})", chunk_idx, start_page, end_page);
 
     // Create chunk file path
     let chunk_file = temp_dir.join(format!("chunk_{}.pdf", chunk_idx));
 
-    // Split PDF using pdftk (most reliable) or qpdf as fallback
-    let success = split_pdf_chunk(file_path, &chunk_file, start_page, end_page)?;
+    // Split PDF using pdftk (most reliable) or qpdf as fallback - both
+    // decrypt an encrypted `source_file` into a plain chunk when given the
+    // password, so `pdf_extract` (which has no password support of its
+    // own) can still read it below.
+    let success = split_pdf_chunk(file_path, &chunk_file, start_page, end_page, password)?;
     
     if !success {
         return Ok(String::new());
     }
 
     // Extract text from the chunk
-    let chunk_text = pdf_extract::extract_text(&chunk_file)?;
-    
+    let mut chunk_text = pdf_extract::extract_text(&chunk_file)?;
+
+    // `pdf_extract` only reads embedded text, so a scanned/image-only
+    // chunk comes back (near) empty. Fall back to rasterizing its pages
+    // and OCR'ing them - each chunk stays independent, so this doesn't
+    // disturb the Rayon parallelism above.
+    let page_count = end_page.saturating_sub(start_page) + 1;
+    let min_chars_per_page = crate::limits::ocr_fallback_min_chars_per_page();
+    if page_count > 0 && chunk_text.trim().len() / page_count < min_chars_per_page {
+        println!(
+            "Chunk {} has {} chars over {} pages (below the {}/page threshold), falling back to OCR",
+            chunk_idx,
+            chunk_text.trim().len(),
+            page_count,
+            min_chars_per_page
+        );
+        match ocr_pdf_chunk(&chunk_file, temp_dir, chunk_idx) {
+            Ok(ocr_text) if !ocr_text.trim().is_empty() => chunk_text = ocr_text,
+            Ok(_) => println!("Warning: OCR fallback for chunk {} produced no text, keeping original extraction", chunk_idx),
+            Err(e) => println!("Warning: OCR fallback for chunk {} failed: {} (keeping original extraction)", chunk_idx, e),
+        }
+    }
+
     // Clean up the chunk file
     let _ = fs::remove_file(&chunk_file);
-    
+
     println!("Completed chunk {} ({} characters)", chunk_idx, chunk_text.len());
     Ok(chunk_text)
 }
 
+/// Rasterizes `chunk_file`'s pages to PNGs (via `crate::ocr`'s existing
+/// `pdftoppm` helper, at `crate::limits::pdf_render_dpi`) and OCRs each
+/// one, for the scanned-PDF case where `pdf_extract` has nothing to read.
+fn ocr_pdf_chunk(chunk_file: &std::path::Path, temp_dir: &Arc<std::path::PathBuf>, chunk_idx: usize) -> Result<String, Box<StdError>> {
+    let images_dir = temp_dir.join(format!("ocr_chunk_{}", chunk_idx));
+    if images_dir.exists() {
+        fs::remove_dir_all(&images_dir)?;
+    }
+    fs::create_dir_all(&images_dir)?;
+
+    let chunk_file_str = chunk_file.to_str().ok_or("chunk file path is not valid UTF-8")?;
+    let image_paths = crate::ocr::convert_pdf_pages_to_images(chunk_file_str, &images_dir)?;
+
+    let mut pages_text = Vec::with_capacity(image_paths.len());
+    for image_path in &image_paths {
+        match crate::ocr::extract_text_with_ocrs(image_path) {
+            Ok(text) if !text.trim().is_empty() => pages_text.push(text),
+            Ok(_) => {}
+            Err(e) => println!("Warning: OCR failed on {}: {}", image_path, e),
+        }
+    }
+
+    let _ = fs::remove_dir_all(&images_dir);
+
+    Ok(pages_text.join("\n\n"))
+}
+
 fn split_pdf_chunk(
     source_file: &str,
     chunk_file: &std::path::Path,
     start_page: usize,
     end_page: usize,
+    password: Option<&str>,
 ) -> Result<bool, Box<StdError>> {
+    let timeout = crate::limits::subprocess_timeout();
+
     // Try pdftk first
-    let pdftk_result = Command::new("pdftk")
-        .arg(source_file)
-        .arg("cat")
-        .arg(format!("{}-{}", start_page, end_page))
-        .arg("output")
-        .arg(chunk_file)
-        .status();
-
-    if pdftk_result.is_ok() && pdftk_result.unwrap().success() {
+    let mut pdftk_cmd = Command::new("pdftk");
+    pdftk_cmd.arg(source_file);
+    if let Some(password) = password {
+        pdftk_cmd.arg("input_pw").arg(password);
+    }
+    let pdftk_result = crate::limits::run_with_timeout(
+        pdftk_cmd
+            .arg("cat")
+            .arg(format!("{}-{}", start_page, end_page))
+            .arg("output")
+            .arg(chunk_file),
+        timeout,
+    );
+
+    if matches!(&pdftk_result, Ok(status) if status.success()) {
         return Ok(true);
     }
 
     // Fallback to qpdf
-    let qpdf_result = Command::new("qpdf")
-        .arg("--pages")
-        .arg(source_file)
-        .arg(format!("{}-{}", start_page, end_page))
-        .arg("--")
-        .arg(chunk_file)
-        .status();
-
-    if qpdf_result.is_ok() && qpdf_result.unwrap().success() {
+    let mut qpdf_cmd = Command::new("qpdf");
+    if let Some(password) = password {
+        qpdf_cmd.arg(format!("--password={}", password));
+    }
+    let qpdf_result = crate::limits::run_with_timeout(
+        qpdf_cmd
+            .arg("--pages")
+            .arg(source_file)
+            .arg(format!("{}-{}", start_page, end_page))
+            .arg("--")
+            .arg(chunk_file),
+        timeout,
+    );
+
+    if matches!(&qpdf_result, Ok(status) if status.success()) {
         return Ok(true);
     }
 
@@ -298,25 +640,29 @@ fn split_pdf_chunk(
     Ok(false)
 }
 
-fn convert_docx_to_pdf(docx_path: &str) -> Result<String, Box<StdError>> {
+/// Converts `docx_path` to PDF, returning `(pdf_path, source_hash)` - the
+/// hash of the *original* DOCX bytes, not the converted PDF's, so callers
+/// can key further caching (e.g. `extract_pdf_text_sync`) off input content
+/// even though PDF generation below isn't guaranteed byte-for-byte
+/// deterministic across runs.
+fn convert_docx_to_pdf(docx_path: &str) -> Result<(String, String), Box<StdError>> {
     let pdfs_dir = Path::new("pdfs");
     if !pdfs_dir.exists() {
         fs::create_dir_all(pdfs_dir)?;
     }
 
-    let base_stem = Path::new(docx_path)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("converted");
-    
-    let pdf_filename = format!("{}.pdf", base_stem);
+    let hash = content_hash_of_file(docx_path)?;
+    let cache_key = cache_key_for(&hash);
+    let pdf_filename = format!("{}.pdf", cache_key);
     let pdf_path = pdfs_dir.join(&pdf_filename);
 
     if pdf_path.exists() {
         println!("Converted PDF already exists at {:?}, using existing file", pdf_path);
-        return Ok(pdf_path.to_string_lossy().to_string());
+        return Ok((pdf_path.to_string_lossy().to_string(), hash));
     }
 
+    write_source_sidecar(pdfs_dir, &cache_key, docx_path)?;
+
     println!("Converting DOCX to PDF: {}", docx_path);
 
     // Read the DOCX file using the correct docx-rs API
@@ -331,9 +677,9 @@ fn convert_docx_to_pdf(docx_path: &str) -> Result<String, Box<StdError>> {
     let content = extract_text_from_docx_bytes(&file_bytes)?;
 
     create_pdf_from_text_content(&pdf_path, &content)?;
-    
+
     println!("Successfully converted DOCX to PDF: {:?}", pdf_path);
-    Ok(pdf_path.to_string_lossy().to_string())
+    Ok((pdf_path.to_string_lossy().to_string(), hash))
 }
 
 // Helper function to extract text from DOCX bytes
@@ -343,7 +689,6 @@ fn extract_text_from_docx_bytes(file_bytes: &[u8]) -> Result<Vec<String>, Box<St
     
     // Convert bytes to string and try to extract readable text
     // This is a basic approach - you might want to use zip crate to properly parse DOCX
-    use std::io::Read;
     use std::io::Cursor;
     
     let cursor = Cursor::new(file_bytes);
@@ -389,25 +734,27 @@ fn extract_text_from_xml(xml_content: &str) -> String {
 
 
 
-fn convert_xlsx_to_pdf(xlsx_path: &str) -> Result<String, Box<StdError>> {
+/// Converts `xlsx_path` to PDF, returning `(pdf_path, source_hash)` - see
+/// `convert_docx_to_pdf` for why the hash is of the source spreadsheet
+/// rather than the generated PDF.
+fn convert_xlsx_to_pdf(xlsx_path: &str) -> Result<(String, String), Box<StdError>> {
     let pdfs_dir = Path::new("pdfs");
     if !pdfs_dir.exists() {
         fs::create_dir_all(pdfs_dir)?;
     }
 
-    let base_stem = Path::new(xlsx_path)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("converted");
-    
-    let pdf_filename = format!("{}.pdf", base_stem);
+    let hash = content_hash_of_file(xlsx_path)?;
+    let cache_key = cache_key_for(&hash);
+    let pdf_filename = format!("{}.pdf", cache_key);
     let pdf_path = pdfs_dir.join(&pdf_filename);
 
     if pdf_path.exists() {
         println!("Converted PDF already exists at {:?}, using existing file", pdf_path);
-        return Ok(pdf_path.to_string_lossy().to_string());
+        return Ok((pdf_path.to_string_lossy().to_string(), hash));
     }
 
+    write_source_sidecar(pdfs_dir, &cache_key, xlsx_path)?;
+
     println!("Converting XLSX to PDF: {}", xlsx_path);
 
     let mut workbook = open_workbook_auto(xlsx_path)
@@ -426,9 +773,9 @@ fn convert_xlsx_to_pdf(xlsx_path: &str) -> Result<String, Box<StdError>> {
     }
 
     create_pdf_from_text_content(&pdf_path, &content)?;
-    
+
     println!("Successfully converted XLSX to PDF: {:?}", pdf_path);
-    Ok(pdf_path.to_string_lossy().to_string())
+    Ok((pdf_path.to_string_lossy().to_string(), hash))
 }
 
 fn convert_range_to_text(range: &Range<DataType>) -> Vec<String> {
@@ -542,9 +889,30 @@ fn wrap_text(text: &str, max_width: f32, _font_size: f32) -> Vec<String> {
     }
 }
 
+/// Default-options wrapper around `extract_file_text_with_opts` for the
+/// common case of an unencrypted document with no page-range selection.
 pub async fn extract_file_text(file_path: &str) -> Result<String, Box<StdError>> {
+    extract_file_text_with_opts(file_path, PdfExtractOpts::default()).await
+}
+
+/// Like `extract_file_text`, but accepts `opts` (a password and/or page
+/// range) for the PDF path - see `PdfExtractOpts`.
+pub async fn extract_file_text_with_opts(file_path: &str, opts: PdfExtractOpts) -> Result<String, Box<StdError>> {
+    let ext = Path::new(file_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    // PPTX extraction OCRs each slide concurrently, so it needs to run on
+    // the async runtime rather than inside spawn_blocking like the other
+    // (fully synchronous) formats below.
+    if ext == "pptx" {
+        return crate::ocr::extract_text_from_pptx(file_path).await;
+    }
+
     let file_path = file_path.to_owned();
-    tokio::task::spawn_blocking(move || extract_file_text_sync(&file_path)).await?
+    tokio::task::spawn_blocking(move || extract_file_text_sync(&file_path, &opts)).await?
 }
 
 
@@ -1,19 +1,40 @@
 use axum::{
-    extract::Json,
+    extract::{Json, Path as AxumPath, State},
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
-use crate::pdf::extract_file_text;
+use uuid::Uuid;
+use crate::pdf::{extract_file_text_with_opts, PdfExtractOpts};
 use crate::pdf::download_file;
-use crate::ai::gemini::{call_gemini_api_with_txts};
-use crate::ai::embed::{get_policy_chunk_embeddings, rewrite_policy_with_context}; // Fixed import
-use std::{env, time::Instant, fs};
+use crate::pdf::load_url;
+use crate::ai::backend::default_backend;
+use crate::ai::embedding::default_embedding_provider;
+use crate::ai::embed::{get_policy_chunk_embeddings, rewrite_policy_with_context, build_ann_index, rank_chunks_with_index, extraction_scope_fingerprint, SearchMode}; // Fixed import
+use crate::error::HackError;
+use crate::jobs::JobQueue;
+use std::{time::Instant, fs};
 
 #[derive(Deserialize)]
 pub struct QuestionRequest {
     pub documents: String,
     pub questions: Vec<String>,
+    /// Forces the recursive web-page loader (`pdf::load_url`) even when
+    /// `documents` happens to have a file extension. When unset, the
+    /// loader is still used automatically for an `http(s)` URL whose path
+    /// has no extension and that serves HTML (see `is_recursive_url_target`
+    /// and `pdf::looks_like_html`); `Some(false)` never crawls.
+    #[serde(default)]
+    pub recursive_url: Option<bool>,
+    /// Owner/user password for an encrypted PDF. Ignored for other
+    /// formats and for `recursive_url` crawls. See `pdf::PdfExtractOpts`.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Inclusive `(first_page, last_page)` window, 1-indexed, to extract
+    /// a single chapter out of a large PDF instead of the whole document.
+    /// See `pdf::PdfExtractOpts`.
+    #[serde(default)]
+    pub page_range: Option<(usize, usize)>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,177 +42,454 @@ pub struct AnswersResponse {
     pub answers: Vec<String>,
 }
 
-pub async fn answer_questions(_pdf_text: &str, questions: &[String], pdf_filename: &str) -> Result<AnswersResponse, Box<dyn std::error::Error>> {
-    let answers = call_gemini_api_with_txts(&questions, pdf_filename).await?;
+pub async fn answer_questions(pdf_text: &str, questions: &[String]) -> Result<AnswersResponse, HackError> {
+    // Backend failures are recoverable: rather than failing the whole batch
+    // with a 500, surface the error as the answer for every question that
+    // was riding on this call so the caller still gets a response shaped
+    // like a success.
+    let backend = default_backend();
+    let answers = match backend.answer(questions, pdf_text).await {
+        Ok(answers) => answers,
+        Err(e) => {
+            let message = HackError::Gemini(e.to_string()).to_string();
+            vec![message; questions.len()]
+        }
+    };
     Ok(AnswersResponse { answers })
 }
 
 
-pub async fn hackrx_run(
-    headers: HeaderMap,
-    Json(body): Json<QuestionRequest>,
-) -> Result<Json<AnswersResponse>, Response> {
+/// Runs the actual download -> extract -> embed -> answer pipeline for a
+/// single request. This used to be the body of the `hackrx_run` handler;
+/// it now also backs the background job worker, so it takes ownership of
+/// the request and reports failures as `HackError` rather than an HTTP
+/// `Response` - the caller (handler or worker) decides how to surface that.
+pub async fn run_pipeline(body: QuestionRequest) -> Result<AnswersResponse, HackError> {
     let start_time = Instant::now();
     println!("Received request with documents URL: {}", body.documents);
 
-    // Authorization check
-    let auth = headers
-        .get("authorization")
-        .and_then(|value| value.to_str().ok());
-
-    if auth.is_none() || !auth.unwrap().starts_with("Bearer ") {
-        println!("Request rejected: Missing or invalid Authorization token");
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            "Missing or invalid Authorization token",
-        )
-            .into_response());
+    println!("Processing document...");
+
+    // `is_recursive_url_target` auto-detection is shape-only (no file
+    // extension -> candidate web page); when it wasn't `recursive_url`
+    // forcing the answer, confirm against the real Content-Type/magic
+    // bytes before committing, so an extension-less link to a PDF/DOCX/
+    // image falls through to `download_file` + `sniff_extension` instead
+    // of being crawled and lossily decoded as HTML text.
+    let mut is_url_crawl = is_recursive_url_target(&body.documents, body.recursive_url);
+    if is_url_crawl && body.recursive_url.is_none() {
+        is_url_crawl = crate::pdf::looks_like_html(&body.documents).await.unwrap_or(false);
     }
 
-    println!("Authorization token accepted, starting PDF download...");
-
-
-    println!("Authorization token accepted, processing document...");
-
     // Generate filename from URL
-    let filename = generate_filename_from_url(&body.documents).await.map_err(|e| {
-
-        println!("Failed to generate filename from URL: {}", e);
-    
-        // Create error response in the same format as successful responses
-        let error_response = AnswersResponse {
-            answers: vec!["Sorry we do not support the file format that you uploaded".to_string()]
-        };
-    
-        (
-        StatusCode::BAD_REQUEST,
-        Json(error_response),
-        )
-        .into_response()
-    })?;
+    let filename = if is_url_crawl {
+        format!("url_{}.txt", hash_url(&body.documents))
+    } else {
+        generate_filename_from_url(&body.documents).await.map_err(|e| {
+            println!("Failed to generate filename from URL: {}", e);
+            HackError::Unsupported("Sorry we do not support the file format that you uploaded".to_string())
+        })?
+    };
 
 
-    let permpath = format!("pdfs/{}", filename);
+    let mut permpath = format!("pdfs/{}", filename);
     println!("Target file path: {}", permpath);
 
-    // Check if file already exists
+    // This is necessarily a filename-stem (i.e. URL-derived) existence
+    // check, not a content-hash one: the content hash can only be
+    // computed from the downloaded bytes, so it can't gate the download
+    // decision itself - two different URLs serving identical bytes will
+    // still each download once. Everything downstream of the download
+    // (extracted text below, and the chunk embeddings in `ai::embed`) is
+    // content-hash keyed through `store`, so that's where the dedup for
+    // identical-content-different-URL documents actually happens.
     let file_exists = Path::new(&permpath).exists();
-    
+
     if file_exists {
         println!("File already exists at {}, skipping download", permpath);
+    } else if is_url_crawl {
+        println!("No file extension in URL, crawling as a web page: {}", body.documents);
+
+        if let Some(parent) = Path::new(&permpath).parent() {
+            std::fs::create_dir_all(parent).map_err(HackError::from)?;
+        }
+
+        let crawled_text = load_url(&body.documents, crate::limits::url_crawl_depth(), true)
+            .await
+            .map_err(|e| {
+                println!("Failed to crawl URL: {}", e);
+                HackError::Download(e.to_string())
+            })?;
+        std::fs::write(&permpath, &crawled_text).map_err(HackError::from)?;
+
+        println!("Crawled and extracted text to {}", permpath);
     } else {
         println!("File not found, downloading from: {}", body.documents);
-        
+
         // Ensure pdfs directory exists
         if let Some(parent) = Path::new(&permpath).parent() {
-            std::fs::create_dir_all(parent).map_err(|e| {
-                println!("Failed to create pdfs directory: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Directory creation error: {}", e),
-                )
-                .into_response()
-            })?;
+            std::fs::create_dir_all(parent).map_err(HackError::from)?;
         }
 
-        download_file(&body.documents, &permpath)
+        let content_type = download_file(&body.documents, &permpath)
             .await
             .map_err(|e| {
                 println!("Failed to download FILE: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("FILE download error: {}", e),
-                )
-                .into_response()
+                HackError::Download(e.to_string())
             })?;
-        
+
         println!("FILE downloaded successfully to {}", permpath);
+
+        // The URL extension is only a guess; sniff the bytes we actually
+        // got (plus the Content-Type header) and rename to the real
+        // extension so extraction routes to the right parser.
+        permpath = crate::sniff::sniff_extension(Path::new(&permpath), content_type.as_deref())
+            .and_then(|real_ext| {
+                let real_path = format!(
+                    "pdfs/{}.{}",
+                    Path::new(&permpath).file_stem().and_then(|s| s.to_str()).unwrap_or("document"),
+                    real_ext
+                );
+                if real_path != permpath {
+                    std::fs::rename(&permpath, &real_path).map_err(HackError::from)?;
+                    println!("Sniffed real file type, renamed {} -> {}", permpath, real_path);
+                }
+                Ok(real_path)
+            })?;
     }
 
     println!("FILE downloaded successfully to {}", permpath);
 
-    // Extract PDF text - this creates pdfs/{permapath}.txt
-    let _pdf_text = extract_file_text(&permpath).await.map_err(|e| {
-        println!("Failed to extract PDF text: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("PDF extraction error: {}", e),
-        )
-        .into_response()
-    })?;
+    // Content-address the downloaded bytes so identical documents served
+    // from different URLs share one cache entry instead of re-running OCR
+    // for each distinct URL. `store` is filesystem-backed by default
+    // (matching today's `pdfs/` behavior) but can be S3-backed via
+    // `STORAGE_BACKEND=s3`, so the cache survives restarts and is shared
+    // across replicas.
+    let store = crate::store::default_store();
+    let document_bytes = fs::read(&permpath).map_err(HackError::from)?;
+    let content_hash = crate::store::hash_bytes(&document_bytes);
+    let doc_ext = Path::new(&permpath).extension().and_then(|s| s.to_str()).unwrap_or("bin");
+    store.put(&crate::store::document_key(&content_hash, doc_ext), &document_bytes).await?;
+
+    let txt_path = format!("pdfs/{}.txt", Path::new(&permpath).file_stem().and_then(|s| s.to_str()).unwrap_or("document"));
+    let extracted_key = crate::store::extracted_text_key(&content_hash);
+
+    let extract_opts = PdfExtractOpts { password: body.password.clone(), page_range: body.page_range };
+    // A password or page range selects a *different* view of the same
+    // bytes than the cached full-document extraction, so content-hash
+    // keyed caching only applies to the default (whole-document,
+    // unencrypted) extraction.
+    let opts_are_default = extract_opts.password.is_none() && extract_opts.page_range.is_none();
+    // Same scoping the MySQL/`Store` embedding caches below need, so a
+    // password/page-range-scoped request can never be served (or serve) the
+    // whole-document embedding set under the same filename/content hash.
+    let embeddings_scope = extraction_scope_fingerprint(extract_opts.password.is_some(), extract_opts.page_range);
+
+    let cached_text = if opts_are_default { store.get(&extracted_key).await? } else { None };
+
+    if let Some(cached_text) = cached_text {
+        println!("Reusing cached extraction for content hash {}", content_hash);
+        std::fs::write(&txt_path, &cached_text).map_err(HackError::from)?;
+    } else if is_url_crawl {
+        // `permpath` already is `txt_path` - the crawler wrote plain text
+        // directly above, so there's nothing left to extract.
+        if opts_are_default {
+            if let Ok(extracted_text) = fs::read(&txt_path) {
+                store.put(&extracted_key, &extracted_text).await?;
+            }
+        }
+    } else {
+        // Extract the document's text and write it to `txt_path`, where the
+        // embedding step reads it (the PDF path caches its own copy under a
+        // content-hash name, so it does not create `txt_path` itself). A
+        // `.txt` document already is `txt_path` and is left as downloaded.
+        // Extraction is a recoverable failure: if it fails (or only
+        // partially succeeds, as with a PPTX whose OCR errors on a handful
+        // of slides) we still want to try to answer questions against
+        // whatever text made it to disk.
+        match extract_file_text_with_opts(&permpath, extract_opts).await {
+            Ok(text) => {
+                if txt_path != permpath {
+                    std::fs::write(&txt_path, &text).map_err(HackError::from)?;
+                }
+            }
+            Err(e) => {
+                let err = HackError::Extraction(e.to_string());
+                println!("Text extraction did not fully succeed: {} (continuing, fatal={})", err, err.is_fatal());
+                if !Path::new(&txt_path).exists() {
+                    std::fs::write(&txt_path, "").map_err(HackError::from)?;
+                }
+            }
+        }
 
-    // Get API key and embedding AFTER text extraction
+        if opts_are_default {
+            if let Ok(extracted_text) = fs::read(&txt_path) {
+                store.put(&extracted_key, &extracted_text).await?;
+            }
+        }
+    }
+
+    // Build the embedding provider AFTER text extraction (selected via
+    // `EMBEDDING_PROVIDER`, defaulting to Gemini - see `ai::embedding`).
     dotenvy::dotenv().ok();
-    let api_key = env::var("GEMINI_KEY").map_err(|_| {
-        println!("GEMINI_KEY not found in environment variables");
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "GEMINI_KEY environment variable not found",
-        )
-            .into_response()
-    })?;
+    let embedding_provider = default_embedding_provider();
 
     let pdf_filename = std::path::Path::new(&permpath)
     .file_stem()
     .and_then(|name| name.to_str())
     .unwrap_or("document");
 
-    let chunk_embeddings = get_policy_chunk_embeddings(&api_key, pdf_filename).await.map_err(|e| {
+    let chunk_embeddings = get_policy_chunk_embeddings(embedding_provider.as_ref(), pdf_filename, Some(&content_hash), embeddings_scope.as_deref()).await.map_err(|e| {
         println!("Failed to get policy chunk embeddings: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Embedding error: {}", e),
-        )
-            .into_response()
+        HackError::Embedding(e.to_string())
     })?;
 
     println!("Got chunk embeddings for {} chunks", chunk_embeddings.len());
     println!("Processing questions and preparing answers...");
 
-    // Rewrite filename.txt with relevant context for questions
-    rewrite_policy_with_context(&api_key, &body.questions, &chunk_embeddings, pdf_filename)
+    // Generate the contextfiltered filename based on the PDF filename
+    let contextfiltered_filename = format!("pdfs/{}_contextfiltered.txt", pdf_filename);
+    let questions_hash = crate::store::hash_bytes(body.questions.join("\u{1f}").as_bytes());
+    let context_key = crate::store::context_filtered_key(&content_hash, &questions_hash);
+
+    if let Some(cached_context) = store.get(&context_key).await? {
+        println!("Reusing cached context-filtered text for this document/question set");
+        std::fs::write(&contextfiltered_filename, &cached_context).map_err(HackError::from)?;
+    } else {
+        // Rewrite filename.txt with relevant context for questions
+        rewrite_policy_with_context(embedding_provider.as_ref(), &body.questions, &chunk_embeddings, pdf_filename)
+            .await
+            .map_err(|e| {
+                println!("Failed to rewrite policy with context: {}", e);
+                HackError::Embedding(e.to_string())
+            })?;
+
+        println!("Policy file rewritten with question contexts");
+
+        if let Ok(context_bytes) = fs::read(&contextfiltered_filename) {
+            store.put(&context_key, &context_bytes).await?;
+        }
+    }
+
+    // Now call your answer function with the rewritten context
+    let updated_pdf_text = fs::read_to_string(&contextfiltered_filename).map_err(HackError::from)?;
+
+    let answers_response = answer_questions(&updated_pdf_text, &body.questions).await?;
+
+    println!("Pipeline finished in {:?}.", start_time.elapsed());
+
+    Ok(answers_response)
+}
+
+/// Shared by every handler that requires the `Authorization: Bearer ...`
+/// check `hackrx_run` originally enforced alone - `search` and
+/// `hackrx_stream` run the same DB-backed retrieval (and, for the latter,
+/// an LLM call) and need the same gate.
+fn require_bearer_auth(headers: &HeaderMap) -> Result<(), Response> {
+    let auth = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok());
+
+    if auth.is_none() || !auth.unwrap().starts_with("Bearer ") {
+        println!("Request rejected: Missing or invalid Authorization token");
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid Authorization token",
+        )
+            .into_response());
+    }
+
+    Ok(())
+}
+
+/// `POST /api/v1/hackrx/run` - enqueues the document pipeline as a
+/// background job and returns immediately with a `job_id` so clients
+/// submitting large/slow documents don't time out waiting on the
+/// connection.
+pub async fn hackrx_run(
+    State(queue): State<JobQueue>,
+    headers: HeaderMap,
+    Json(body): Json<QuestionRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    require_bearer_auth(&headers)?;
+
+    let job_id = queue.submit(body).await;
+    println!("Enqueued job {}", job_id);
+
+    Ok(Json(serde_json::json!({ "job_id": job_id })))
+}
+
+/// `GET /api/v1/hackrx/jobs/{id}` - polls the state of a previously
+/// enqueued job.
+pub async fn get_job(
+    State(queue): State<JobQueue>,
+    AxumPath(job_id): AxumPath<Uuid>,
+) -> Result<Json<crate::jobs::JobState>, Response> {
+    match queue.get(&job_id).await {
+        Some(state) => Ok(Json(state)),
+        None => Err((StatusCode::NOT_FOUND, "No such job").into_response()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SearchRequest {
+    pub pdf_filename: String,
+    pub queries: Vec<String>,
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Results scoring below this are dropped. Note `mode` changes what
+    /// scale `score` (and so this) lives on: `Vector` scores are cosine
+    /// similarity in `[-1, 1]`; `Hybrid` scores are an unbounded (but
+    /// small) fused RRF value.
+    #[serde(default)]
+    pub min_score: f32,
+}
+
+fn default_top_k() -> usize {
+    10
+}
+
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub score: f32,
+    pub chunk_text: String,
+    pub start_offset: i32,
+    pub end_offset: i32,
+}
+
+#[derive(Serialize)]
+pub struct SearchQueryResult {
+    pub query: String,
+    pub hits: Vec<SearchHit>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    pub results: Vec<SearchQueryResult>,
+}
+
+/// `POST /api/v1/search` - semantic (or hybrid) search over a document's
+/// already-embedded chunks, returned synchronously as ranked JSON rather
+/// than written to a `_contextfiltered.txt` file like `hackrx_run`'s
+/// pipeline does. Expects `pdf_filename` to already have embeddings
+/// stored (i.e. the document has already been through `hackrx_run` at
+/// least once) - this endpoint doesn't download or extract anything
+/// itself, only retrieves.
+pub async fn search(headers: HeaderMap, Json(body): Json<SearchRequest>) -> Result<Json<SearchResponse>, Response> {
+    require_bearer_auth(&headers)?;
+
+    dotenvy::dotenv().ok();
+    let embedding_provider = default_embedding_provider();
+
+    let chunk_embeddings = get_policy_chunk_embeddings(embedding_provider.as_ref(), &body.pdf_filename, None, None)
         .await
         .map_err(|e| {
-            println!("Failed to rewrite policy with context: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Context rewriting error: {}", e),
-            )
-                .into_response()
+            (StatusCode::NOT_FOUND, format!("No embeddings found for {}: {}", body.pdf_filename, e)).into_response()
         })?;
 
-    println!("Policy file rewritten with question contexts");
+    // Built once and reused across every query below - rebuilding it per
+    // query would re-insert every chunk's embedding on each loop
+    // iteration for no benefit, since all queries search the same chunk
+    // set.
+    let ann_index = build_ann_index(&chunk_embeddings);
+
+    let mut results = Vec::with_capacity(body.queries.len());
+    for query in &body.queries {
+        let ranked = rank_chunks_with_index(
+            embedding_provider.as_ref(),
+            query,
+            &chunk_embeddings,
+            &ann_index,
+            body.mode,
+            body.top_k,
+            body.min_score,
+        )
+        .await
+            .map_err(|e| {
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("Search failed for query {:?}: {}", query, e)).into_response()
+            })?;
 
+        let hits = ranked
+            .into_iter()
+            .map(|(score, chunk_text, start_offset, end_offset)| SearchHit { score, chunk_text, start_offset, end_offset })
+            .collect();
 
-    // Generate the contextfiltered filename based on the PDF filename
-    let pdf_filename = std::path::Path::new(&permpath)
-    .file_stem()
-    .and_then(|name| name.to_str())
-    .unwrap_or("document");
-    let contextfiltered_filename = format!("pdfs/{}_contextfiltered.txt", pdf_filename);
+        results.push(SearchQueryResult { query: query.clone(), hits });
+    }
 
-    // Now call your answer function with the rewritten context
-    let updated_pdf_text = fs::read_to_string(&contextfiltered_filename).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to read updated policy: {}", e),
-        )
-            .into_response()
-    })?;
+    Ok(Json(SearchResponse { results }))
+}
+
+#[derive(Deserialize)]
+pub struct StreamRequest {
+    pub pdf_filename: String,
+    pub questions: Vec<String>,
+}
+
+/// `POST /api/v1/hackrx/stream` - like `hackrx_run`, but streams the
+/// answer text back as Server-Sent Events instead of enqueuing a job and
+/// polling for the final result. Only backends with a streaming API
+/// support this (currently Gemini - see `TransformerBackend::answer_stream`);
+/// others fail the request up front. Like `/api/v1/search`, this expects
+/// `pdf_filename` to already have embeddings stored from a prior
+/// `hackrx_run`.
+pub async fn hackrx_stream(
+    headers: HeaderMap,
+    Json(body): Json<StreamRequest>,
+) -> Result<axum::response::sse::Sse<impl futures_util::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>>, Response> {
+    use axum::response::sse::{Event, Sse};
+    use futures_util::stream::unfold;
 
-    let answers_response = answer_questions(&updated_pdf_text, &body.questions, pdf_filename)
+    require_bearer_auth(&headers)?;
+
+    dotenvy::dotenv().ok();
+    let embedding_provider = default_embedding_provider();
+
+    let chunk_embeddings = get_policy_chunk_embeddings(embedding_provider.as_ref(), &body.pdf_filename, None, None)
         .await
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Answering questions error: {}", e),
-            )
-                .into_response()
+            (StatusCode::NOT_FOUND, format!("No embeddings found for {}: {}", body.pdf_filename, e)).into_response()
         })?;
 
-    println!("Request processed successfully in {:?}. Sending response.", start_time.elapsed());
+    let combined_questions = body.questions.join(" ");
+    let ranked = rank_chunks_with_index(
+        embedding_provider.as_ref(),
+        &combined_questions,
+        &chunk_embeddings,
+        &build_ann_index(&chunk_embeddings),
+        SearchMode::Hybrid,
+        default_top_k(),
+        f32::NEG_INFINITY,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Retrieval failed: {}", e)).into_response())?;
+
+    let context = if ranked.is_empty() {
+        "No highly relevant context found for these questions.".to_string()
+    } else {
+        ranked.iter().map(|(_, text, _, _)| text.as_str()).collect::<Vec<_>>().join("\n\n---\n\n")
+    };
+
+    let backend = default_backend();
+    let rx = backend
+        .answer_stream(&body.questions, &context)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Streaming not available: {}", e)).into_response())?;
+
+    let stream = unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| {
+            let event = match item {
+                Ok(text) => Event::default().data(text),
+                Err(e) => Event::default().event("error").data(e.to_string()),
+            };
+            (Ok(event), rx)
+        })
+    });
 
-    Ok(Json(answers_response))
+    Ok(Sse::new(stream))
 }
 
 use std::path::Path;
@@ -201,6 +499,26 @@ use url::Url;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 
+/// True when `documents` should be routed to the recursive web-page loader
+/// (`pdf::load_url`) instead of the download-then-extract file path: an
+/// explicit `recursive_url: true` on the request, or an `http(s)` URL whose
+/// last path segment has no file extension at all.
+fn is_recursive_url_target(documents: &str, recursive_url: Option<bool>) -> bool {
+    if let Some(forced) = recursive_url {
+        return forced;
+    }
+
+    let Ok(parsed) = Url::parse(documents) else {
+        return false;
+    };
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return false;
+    }
+
+    let last_segment = parsed.path_segments().and_then(|mut s| s.next_back()).unwrap_or("");
+    Path::new(last_segment).extension().is_none()
+}
+
 async fn generate_filename_from_url(url: &str) -> Result<String, Box<dyn std::error::Error>> {
     let parsed_url = Url::parse(url)?;
     
@@ -0,0 +1,184 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::HackError;
+
+/// Sniff the true file type of a downloaded document from its magic bytes,
+/// falling back to the HTTP `Content-Type` header when the bytes alone
+/// aren't conclusive (e.g. a `.txt`/`.json` file has no signature).
+///
+/// Returns the file extension (without the dot) that the rest of the
+/// pipeline should treat the file as, or a `HackError::Unsupported` if the
+/// bytes match a format we explicitly don't support.
+pub fn sniff_extension(path: &Path, content_type: Option<&str>) -> Result<String, HackError> {
+    let mut file = File::open(path).map_err(HackError::from)?;
+    let mut header = [0u8; 512];
+    let n = file.read(&mut header).map_err(HackError::from)?;
+    let header = &header[..n];
+
+    if header.starts_with(b"%PDF") {
+        return Ok("pdf".to_string());
+    }
+    if header.starts_with(b"\x89PNG") {
+        return Ok("png".to_string());
+    }
+    if header.starts_with(b"\xFF\xD8\xFF") {
+        return Ok("jpeg".to_string());
+    }
+    if header.starts_with(b"PK\x03\x04") {
+        return sniff_zip_based(path);
+    }
+
+    // No recognized binary signature - trust the Content-Type header for
+    // plain-text formats, which have no magic bytes of their own. With no
+    // Content-Type at all there's no signal either way, so (as before)
+    // assume plain text. But an explicit Content-Type that isn't one of
+    // the text-ish formats we actually handle - a CDN's `text/html` error
+    // page, a generic `application/octet-stream` blob, ... - is rejected
+    // rather than silently treated as extractable text, which would
+    // otherwise produce garbage answers with no indication anything went
+    // wrong.
+    match content_type.map(|c| c.to_lowercase()) {
+        Some(ct) if ct.contains("json") => Ok("json".to_string()),
+        Some(ct) if ct.contains("xml") => Ok("xml".to_string()),
+        Some(ct) if ct.contains("text/plain") => Ok("txt".to_string()),
+        None => Ok("txt".to_string()),
+        Some(ct) => Err(HackError::Unsupported(format!(
+            "We don't support this file type (no recognized magic bytes, and Content-Type {:?} isn't plain text/json/xml).",
+            ct
+        ))),
+    }
+}
+
+/// A ZIP magic number alone is ambiguous: docx/pptx/xlsx/plain-zip all share
+/// it. Disambiguate by inspecting `[Content_Types].xml` and the well-known
+/// internal paths Office uses for each format.
+fn sniff_zip_based(path: &Path) -> Result<String, HackError> {
+    let file = File::open(path).map_err(HackError::from)?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| HackError::Unsupported(format!("not a valid zip/office archive: {}", e)))?;
+
+    let has_entry = |archive: &mut zip::ZipArchive<File>, name: &str| archive.by_name(name).is_ok();
+
+    if has_entry(&mut archive, "word/document.xml") {
+        return Ok("docx".to_string());
+    }
+    if has_entry(&mut archive, "ppt/presentation.xml") {
+        return Ok("pptx".to_string());
+    }
+    if has_entry(&mut archive, "xl/workbook.xml") {
+        return Ok("xlsx".to_string());
+    }
+
+    // No recognizable office structure inside the archive - treat it like
+    // the plain zip/bin files we already reject.
+    Err(HackError::Unsupported(
+        "We don't support this file type. ZIP and BIN files are not supported.".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    /// A scratch file under the system temp dir, removed on drop - same
+    /// `env::temp_dir()` + `Uuid` convention `loaders.rs` uses for its own
+    /// temp output files.
+    struct TempFile(std::path::PathBuf);
+
+    impl TempFile {
+        fn with_bytes(bytes: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("sniff_test_{}", Uuid::new_v4()));
+            std::fs::write(&path, bytes).expect("failed to write temp file");
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn sniffs_pdf_from_magic_bytes() {
+        let file = TempFile::with_bytes(b"%PDF-1.4 rest of the file content");
+        assert_eq!(sniff_extension(&file.0, None).unwrap(), "pdf");
+    }
+
+    #[test]
+    fn sniffs_png_from_magic_bytes() {
+        let file = TempFile::with_bytes(b"\x89PNG\r\n\x1a\nrest of png data");
+        assert_eq!(sniff_extension(&file.0, None).unwrap(), "png");
+    }
+
+    #[test]
+    fn sniffs_jpeg_from_magic_bytes() {
+        let file = TempFile::with_bytes(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10]);
+        assert_eq!(sniff_extension(&file.0, None).unwrap(), "jpeg");
+    }
+
+    #[test]
+    fn falls_back_to_content_type_when_no_magic_bytes_match() {
+        let file = TempFile::with_bytes(b"{\"not\": \"binary\"}");
+        assert_eq!(sniff_extension(&file.0, Some("application/json")).unwrap(), "json");
+
+        let file = TempFile::with_bytes(b"plain text body");
+        assert_eq!(sniff_extension(&file.0, None).unwrap(), "txt");
+    }
+
+    #[test]
+    fn rejects_unrecognized_binary_with_unhelpful_content_type() {
+        let file = TempFile::with_bytes(b"<html><body>404 Not Found</body></html>");
+        let err = sniff_extension(&file.0, Some("text/html; charset=utf-8")).unwrap_err();
+        assert!(matches!(err, HackError::Unsupported(_)));
+
+        let file = TempFile::with_bytes(&[0x00, 0x01, 0x02, 0x03]);
+        let err = sniff_extension(&file.0, Some("application/octet-stream")).unwrap_err();
+        assert!(matches!(err, HackError::Unsupported(_)));
+    }
+
+    /// Builds a minimal in-memory zip archive whose only entry is `entry_name`
+    /// (content doesn't matter - `sniff_zip_based` only checks presence), for
+    /// exercising the docx/pptx/xlsx disambiguation without shipping real
+    /// fixture files.
+    fn zip_with_entry(entry_name: &str) -> Vec<u8> {
+        let mut buf = std::io::Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buf);
+            let options = zip::write::FileOptions::default();
+            writer.start_file(entry_name, options).expect("failed to start zip entry");
+            use std::io::Write;
+            writer.write_all(b"placeholder").expect("failed to write zip entry");
+            writer.finish().expect("failed to finish zip archive");
+        }
+        buf.into_inner()
+    }
+
+    #[test]
+    fn sniffs_docx_from_archive_structure() {
+        let file = TempFile::with_bytes(&zip_with_entry("word/document.xml"));
+        assert_eq!(sniff_extension(&file.0, None).unwrap(), "docx");
+    }
+
+    #[test]
+    fn sniffs_pptx_from_archive_structure() {
+        let file = TempFile::with_bytes(&zip_with_entry("ppt/presentation.xml"));
+        assert_eq!(sniff_extension(&file.0, None).unwrap(), "pptx");
+    }
+
+    #[test]
+    fn sniffs_xlsx_from_archive_structure() {
+        let file = TempFile::with_bytes(&zip_with_entry("xl/workbook.xml"));
+        assert_eq!(sniff_extension(&file.0, None).unwrap(), "xlsx");
+    }
+
+    #[test]
+    fn rejects_zip_with_no_recognizable_office_structure() {
+        let file = TempFile::with_bytes(&zip_with_entry("some/random/file.txt"));
+        let err = sniff_extension(&file.0, None).unwrap_err();
+        assert!(matches!(err, HackError::Unsupported(_)));
+    }
+}
@@ -0,0 +1,214 @@
+use std::env;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use crate::error::HackError;
+
+/// A content-addressed blob store. Downloaded documents and the
+/// intermediate text artifacts derived from them (OCR/extracted text,
+/// context-filtered text) are all keyed by a content hash rather than a
+/// local path, so the cache is shareable across API replicas and survives
+/// container restarts when backed by `S3Store`.
+///
+/// Async so `S3Store` can drive its `reqwest` round-trips directly on the
+/// calling task instead of blocking a worker thread via
+/// `block_in_place`/`block_on` - `FilesystemStore`'s methods just don't
+/// `.await` anything, which is fine for an async fn.
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<(), HackError>;
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, HackError>;
+    async fn exists(&self, key: &str) -> Result<bool, HackError>;
+    /// Where the object currently lives, for logging/debugging - a local
+    /// path for `FilesystemStore`, an `s3://` URI for `S3Store`.
+    fn url_for(&self, key: &str) -> String;
+}
+
+/// Builds the store selected by the `STORAGE_BACKEND` env var
+/// (`filesystem` (default) or `s3`), matching how the rest of the crate
+/// reads its config (`GEMINI_KEY`, `MYSQL_CONNECTION`, ...) from the
+/// environment.
+pub fn default_store() -> Box<dyn Store> {
+    match env::var("STORAGE_BACKEND").unwrap_or_default().to_lowercase().as_str() {
+        "s3" => Box::new(S3Store::from_env()),
+        _ => Box::new(FilesystemStore::new("pdfs")),
+    }
+}
+
+/// Hashes `bytes` with SHA-256, for content-addressing downloaded
+/// documents. This is the byte-content equivalent of the existing
+/// `hash_url` helper in `server.rs`, which only hashes the URL - hashing
+/// the bytes instead means identical documents served from different URLs
+/// dedup to the same key.
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Preserves today's behavior: objects live as plain files under a base
+/// directory (`pdfs/` by default).
+pub struct FilesystemStore {
+    base_dir: PathBuf,
+}
+
+impl FilesystemStore {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self { base_dir: base_dir.into() }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.base_dir.join(key)
+    }
+}
+
+#[async_trait]
+impl Store for FilesystemStore {
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<(), HackError> {
+        let path = self.path_for(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(HackError::from)?;
+        }
+        std::fs::write(path, bytes).map_err(HackError::from)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, HackError> {
+        let path = self.path_for(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(std::fs::read(path).map_err(HackError::from)?))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, HackError> {
+        Ok(self.path_for(key).exists())
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        self.path_for(key).to_string_lossy().to_string()
+    }
+}
+
+/// S3-compatible backend, selected with `STORAGE_BACKEND=s3`. Configured
+/// from `S3_BUCKET`, `S3_ENDPOINT`, `S3_REGION`, `S3_ACCESS_KEY` and
+/// `S3_SECRET_KEY` - the same "read it from the environment" convention
+/// the rest of the crate uses for its credentials.
+pub struct S3Store {
+    bucket: String,
+    endpoint: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3Store {
+    pub fn from_env() -> Self {
+        Self {
+            bucket: env::var("S3_BUCKET").unwrap_or_default(),
+            endpoint: env::var("S3_ENDPOINT").unwrap_or_default(),
+            region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            access_key: env::var("S3_ACCESS_KEY").unwrap_or_default(),
+            secret_key: env::var("S3_SECRET_KEY").unwrap_or_default(),
+        }
+    }
+
+    fn credentials(&self) -> rusty_s3::Credentials {
+        rusty_s3::Credentials::new(&self.access_key, &self.secret_key)
+    }
+
+    fn bucket_handle(&self) -> Result<rusty_s3::Bucket, HackError> {
+        let endpoint = self
+            .endpoint
+            .parse()
+            .map_err(|e| HackError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid S3_ENDPOINT: {}", e))))?;
+        rusty_s3::Bucket::new(endpoint, rusty_s3::UrlStyle::Path, self.bucket.clone(), self.region.clone())
+            .map_err(|e| HackError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("invalid S3 bucket config: {}", e))))
+    }
+}
+
+#[async_trait]
+impl Store for S3Store {
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<(), HackError> {
+        let bucket = self.bucket_handle()?;
+        let credentials = self.credentials();
+        let action = bucket.put_object(Some(&credentials), key);
+        let url = action.sign(std::time::Duration::from_secs(60));
+
+        let client = reqwest::Client::new();
+        let resp = client.put(url).body(bytes.to_vec()).send().await.map_err(|e| {
+            HackError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("S3 PUT failed: {}", e)))
+        })?;
+        if !resp.status().is_success() {
+            return Err(HackError::Io(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("S3 PUT returned {}", resp.status()),
+            )));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, HackError> {
+        let bucket = self.bucket_handle()?;
+        let credentials = self.credentials();
+        let action = bucket.get_object(Some(&credentials), key);
+        let url = action.sign(std::time::Duration::from_secs(60));
+
+        let client = reqwest::Client::new();
+        let resp = client.get(url).send().await.map_err(|e| {
+            HackError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("S3 GET failed: {}", e)))
+        })?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(HackError::Io(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("S3 GET returned {}", resp.status()),
+            )));
+        }
+        let bytes = resp.bytes().await.map_err(|e| {
+            HackError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("S3 GET body read failed: {}", e)))
+        })?;
+        Ok(Some(bytes.to_vec()))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, HackError> {
+        Ok(self.get(key).await?.is_some())
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket, key)
+    }
+}
+
+/// Derives a stable storage key for a downloaded document from its
+/// content hash, keeping the extension so downstream extraction can still
+/// dispatch on file type.
+pub fn document_key(content_hash: &str, ext: &str) -> String {
+    format!("documents/{}.{}", content_hash, ext)
+}
+
+pub fn extracted_text_key(content_hash: &str) -> String {
+    format!("extracted/{}.txt", content_hash)
+}
+
+pub fn context_filtered_key(content_hash: &str, questions_hash: &str) -> String {
+    format!("contextfiltered/{}_{}.txt", content_hash, questions_hash)
+}
+
+/// Keys a document's serialized chunk embeddings by content hash and
+/// embedding model, so two different URLs/filenames that happen to serve
+/// identical bytes share one cached embedding set instead of each paying
+/// to re-embed from scratch. `scope`, when set, folds in a fingerprint of
+/// any non-default extraction options (password/page range - see
+/// `ai::embed::extraction_scope_fingerprint`) so a request scoped to part
+/// of a document never shares a cache entry with the whole-document
+/// embedding set.
+pub fn embeddings_key(content_hash: &str, model: &str, scope: Option<&str>) -> String {
+    match scope {
+        Some(scope) => format!("embeddings/{}_{}_{}.json", content_hash, model, scope),
+        None => format!("embeddings/{}_{}.json", content_hash, model),
+    }
+}